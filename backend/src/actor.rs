@@ -0,0 +1,261 @@
+//! Per-session actors for the Bible Challenge backend server.
+//! Each session runs as its own task that owns the `Session`, its connected
+//! WebSocket clients and its broadcast history. HTTP handlers, WebSocket
+//! connections, timers and the cleanup task send it commands over a channel
+//! and the actor runs them one at a time, so a mutation and the broadcasts
+//! it causes happen together with nothing in between, and sessions never
+//! wait on each other.
+//!
+//! `AppState.sessions` only maps session IDs to actor handles; it is written
+//! when a session starts or goes away. An actor that dies from a panicking job
+//! takes its session with it, rather than leaving a handle nobody answers.
+
+use crate::expiry;
+use crate::history::EventLog;
+use crate::models::{AppState, Session, WsClient, WsServerMsg};
+use crate::outbox;
+use crate::rate_limit::SessionLimit;
+use crate::routes::send_to;
+use crate::store::persist_session;
+use crate::timers;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+/// How many commands may wait for a session before senders have to wait too.
+const COMMAND_QUEUE: usize = 64;
+
+type Job = Box<dyn FnOnce(&mut SessionActor) + Send>;
+
+enum Command {
+    /// Runs a job against the session; the session is persisted afterwards
+    /// if the job marked it dirty.
+    Run(Job),
+    /// Broadcasts `notice`, writes a final snapshot if `persist` is set,
+    /// drops every client and stops the actor.
+    Stop {
+        notice: WsServerMsg,
+        persist: bool,
+        done: oneshot::Sender<()>,
+    },
+}
+
+/// The sending side of a session actor's command channel.
+#[derive(Clone)]
+pub struct SessionHandle {
+    tx: mpsc::Sender<Command>,
+}
+
+impl SessionHandle {
+    /// Runs `job` on the actor and returns its result, or `None` if the actor
+    /// has stopped.
+    ///
+    /// Jobs run synchronously on the actor task, so they can never wait on
+    /// another job for the same session.
+    pub async fn call<R: Send + 'static>(
+        &self,
+        job: impl FnOnce(&mut SessionActor) -> R + Send + 'static,
+    ) -> Option<R> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let job: Job = Box::new(move |actor| {
+            let _ = reply_tx.send(job(actor));
+        });
+        self.tx.send(Command::Run(job)).await.ok()?;
+        reply_rx.await.ok()
+    }
+
+    /// Stops the actor once the commands already queued have run, after
+    /// broadcasting `notice` and, with `persist`, saving the session.
+    pub async fn stop(&self, notice: WsServerMsg, persist: bool) {
+        let (done_tx, done_rx) = oneshot::channel();
+        let command = Command::Stop {
+            notice,
+            persist,
+            done: done_tx,
+        };
+        if self.tx.send(command).await.is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+
+    /// Whether both handles lead to the same actor.
+    pub fn same_actor(&self, other: &SessionHandle) -> bool {
+        self.tx.same_channel(&other.tx)
+    }
+}
+
+/// Everything a session's actor owns. Jobs get exclusive access to it.
+pub struct SessionActor {
+    pub state: Arc<AppState>,
+    pub session_id: String,
+    pub session: Session,
+    /// Connected WebSocket clients, in connection order.
+    pub clients: Vec<WsClient>,
+    /// Recent broadcasts, for `?since=` resync.
+    pub log: EventLog,
+    /// The expiry time clients were last warned about with `SessionExpiring`.
+    pub expiry_warned: Option<DateTime<Utc>>,
+    /// The bucket every connection to the session draws from.
+    pub rate_limit: SessionLimit,
+    dirty: bool,
+}
+
+impl SessionActor {
+    /// Marks the session modified; it is persisted once the current job ends.
+    pub fn touch(&mut self) {
+        self.keep_alive();
+        self.mark_dirty();
+    }
+
+    /// Restarts the idle countdown without persisting, e.g. when a client
+    /// connects or sends `KeepAlive`.
+    pub fn keep_alive(&mut self) {
+        self.session.last_modified = Utc::now();
+        expiry::cancel_stale_warning(self);
+    }
+
+    /// Persists the session once the current job ends, without counting as
+    /// activity for idle cleanup.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Broadcasts a server message to every client, stamped with the session's
+    /// next `seq` and recorded for resync.
+    /// Drops clients whose send channel has been closed, or that are being
+    /// disconnected as slow consumers.
+    pub fn broadcast(&mut self, msg: &WsServerMsg) {
+        match self.log.record(msg) {
+            Ok(payloads) => self.clients.retain(|client| {
+                outbox::deliver(client, payloads.for_role(client.role).to_string())
+            }),
+            Err(err) => eprintln!("Failed to serialize WS server msg for broadcast: {}", err),
+        }
+    }
+
+    /// Sends a message to every client without recording it for resync, for
+    /// notices that would be stale by the time anyone replayed them.
+    pub fn notify(&self, msg: &WsServerMsg) {
+        for client in &self.clients {
+            send_to(client, msg);
+        }
+    }
+
+    /// The connected client with this connection ID.
+    pub fn client(&self, conn_id: Uuid) -> Option<&WsClient> {
+        self.clients.iter().find(|client| client.conn_id == conn_id)
+    }
+}
+
+/// Starts an actor for `session` and returns its handle. The caller adds the
+/// handle to `AppState.sessions`.
+pub fn spawn(state: &Arc<AppState>, session_id: String, session: Session) -> SessionHandle {
+    let (tx, rx) = mpsc::channel(COMMAND_QUEUE);
+    let actor = SessionActor {
+        state: state.clone(),
+        session_id: session_id.clone(),
+        session,
+        clients: Vec::new(),
+        log: EventLog::new(),
+        expiry_warned: None,
+        rate_limit: SessionLimit::new(&state.rate_limit),
+        dirty: false,
+    };
+    let handle = SessionHandle { tx };
+    let task = tokio::spawn(run(actor, rx));
+    let (state, watched) = (state.clone(), handle.clone());
+    tokio::spawn(async move {
+        if let Err(err) = task.await
+            && err.is_panic()
+        {
+            eprintln!("Session {} stopped after a job panicked; removing it", session_id);
+            forget_handle(&state, &session_id, &watched).await;
+        }
+    });
+    handle
+}
+
+/// Removes `handle` from `AppState.sessions` if it is still the session's
+/// current handle.
+async fn forget_handle(state: &AppState, session_id: &str, handle: &SessionHandle) {
+    let mut sessions = state.sessions.write().await;
+    if sessions
+        .get(session_id)
+        .is_some_and(|current| current.same_actor(handle))
+    {
+        sessions.remove(session_id);
+    }
+}
+
+async fn run(mut actor: SessionActor, mut rx: mpsc::Receiver<Command>) {
+    // A restored session may have had a timer running when the server stopped.
+    timers::ensure_ticker(&mut actor);
+    while let Some(command) = rx.recv().await {
+        match command {
+            Command::Run(job) => {
+                job(&mut actor);
+                if actor.dirty {
+                    actor.dirty = false;
+                    persist_session(&actor.state, &actor.session_id, &actor.session).await;
+                }
+            }
+            Command::Stop {
+                notice,
+                persist,
+                done,
+            } => {
+                actor.broadcast(&notice);
+                if persist {
+                    persist_session(&actor.state, &actor.session_id, &actor.session).await;
+                }
+                actor.clients.clear();
+                let _ = done.send(());
+                return;
+            }
+        }
+    }
+}
+
+/// Runs `job` on a session's actor. Returns `None` if the session doesn't
+/// exist, or its actor has stopped, in which case the session is dropped.
+pub async fn call<R: Send + 'static>(
+    state: &AppState,
+    session_id: &str,
+    job: impl FnOnce(&mut SessionActor) -> R + Send + 'static,
+) -> Option<R> {
+    let handle = state.sessions.read().await.get(session_id)?.clone();
+    let result = handle.call(job).await;
+    if result.is_none() {
+        forget_handle(state, session_id, &handle).await;
+    }
+    result
+}
+
+/// Removes a session and stops its actor once it has told its clients.
+/// Returns false if the session didn't exist.
+pub async fn close(state: &AppState, session_id: &str) -> bool {
+    let Some(handle) = state.sessions.write().await.remove(session_id) else {
+        return false;
+    };
+    handle.stop(WsServerMsg::SessionClosed, false).await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_panicking_job_takes_its_session_away() {
+        let state = AppState::new(None);
+        let handle = spawn(&state, "ABCD".to_string(), Session::new());
+        state.sessions.write().await.insert("ABCD".to_string(), handle);
+
+        assert_eq!(call(&state, "ABCD", |actor| actor.session.teams.len()).await, Some(3));
+        let panicked = call(&state, "ABCD", |_| -> () { panic!("job failed") }).await;
+        assert!(panicked.is_none());
+        assert!(state.sessions.read().await.get("ABCD").is_none());
+        assert!(call(&state, "ABCD", |_| ()).await.is_none());
+    }
+}
@@ -0,0 +1,82 @@
+//! Host authentication for the Bible Challenge backend server.
+//! `POST /session/start` hands the creator a secret host token. Host-only HTTP
+//! routes require it as `Authorization: Bearer <token>`, and WebSocket clients
+//! present it as `?host_token=<token>` to connect with the host role.
+
+use crate::actor;
+use crate::models::AppState;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Generates a new secret host token.
+pub fn generate_host_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Extracts a bearer token from the `Authorization` header.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// Checks a presented token against the session's host token.
+///
+/// An empty token never matches, so sessions persisted before host tokens
+/// existed have no host rather than letting anyone in.
+pub fn token_matches(host_token: &str, presented: Option<&str>) -> bool {
+    !host_token.is_empty() && presented == Some(host_token)
+}
+
+/// Returns whether `presented` is the host token for `session_id`.
+/// `None` means the session does not exist.
+pub async fn is_host(state: &AppState, session_id: &str, presented: Option<&str>) -> Option<bool> {
+    let presented = presented.map(str::to_string);
+    actor::call(state, session_id, move |actor| {
+        token_matches(&actor.session.host_token, presented.as_deref())
+    })
+    .await
+}
+
+/// Route layer for host-only HTTP routes under `/session/:id/...`.
+///
+/// Unknown sessions are answered `404` here, so no host-only handler runs
+/// without a checked token.
+pub async fn require_host_token<B>(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<HashMap<String, String>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(session_id) = params.get("id") else {
+        return (StatusCode::NOT_FOUND, Json("Session not found")).into_response();
+    };
+    let presented = bearer_token(request.headers());
+    match is_host(&state, session_id, presented).await {
+        Some(true) => next.run(request).await,
+        Some(false) => (StatusCode::UNAUTHORIZED, Json("Host token required")).into_response(),
+        None => (StatusCode::NOT_FOUND, Json("Session not found")).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_exact_non_empty_token_matches() {
+        assert!(token_matches("secret", Some("secret")));
+        assert!(!token_matches("secret", Some("other")));
+        assert!(!token_matches("secret", None));
+        assert!(!token_matches("", None));
+        assert!(!token_matches("", Some("")));
+    }
+}
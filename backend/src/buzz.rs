@@ -0,0 +1,419 @@
+//! Buzz arbitration for the Bible Challenge backend server.
+//! Buzzes arriving within a short window are collected, each press time is
+//! corrected with its connection's clock offset, and the lock goes to the
+//! earliest corrected press rather than the first packet to arrive. Every
+//! press after that joins a ranked queue the host can pass the lock down.
+
+use crate::actor::{self, SessionActor};
+use crate::clock;
+use crate::command::Caller;
+use crate::daily_double;
+use crate::early_buzz;
+use crate::models::{
+    AppState, BuzzCandidate, BuzzQueueEntry, BuzzResult, ErrorCode, GamePhase, Session, WsError, WsServerMsg,
+};
+use crate::phase;
+use crate::timers;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::sync::Arc;
+use tokio::time::Duration;
+
+/// Upper bound on how far before its arrival a press may be credited.
+/// Stops a client from winning with a forged early timestamp.
+const MAX_COMPENSATION_MS: i64 = 500;
+
+/// The longest arbitration window the host may set. Longer windows would
+/// hold every buzz back noticeably.
+pub const MAX_WINDOW_MS: u32 = 2000;
+
+/// Result of submitting a buzz.
+pub enum BuzzOutcome {
+    /// The buzz is in the current arbitration window (or already won it).
+    Accepted,
+    /// Another team holds the lock; the buzz was added to the queue.
+    Queued,
+    /// Buzzers are locked or not yet open, the team already buzzed or is
+    /// locked out, or it is already in the window.
+    Rejected(WsError),
+    /// The session or team does not exist.
+    NotFound(WsError),
+}
+
+/// Submits a buzz for arbitration.
+///
+/// A player `caller` must be seated at the team. A buzz from a WebSocket
+/// connection has its clock offset applied; HTTP buzzes are credited at their
+/// arrival time.
+pub async fn submit_buzz(
+    state: &Arc<AppState>,
+    session_id: &str,
+    caller: Caller,
+    team_index: usize,
+    client_timestamp: Option<String>,
+) -> BuzzOutcome {
+    let received_at = Utc::now();
+    actor::call(state, session_id, move |actor| {
+        if let Err(err) = caller.require_seat(actor, team_index) {
+            return BuzzOutcome::Rejected(err);
+        }
+        let offset_ms = caller
+            .conn_id
+            .and_then(|conn_id| actor.client(conn_id))
+            .and_then(clock::offset_ms);
+        arbitrate(actor, team_index, client_timestamp, received_at, offset_ms)
+    })
+    .await
+    .unwrap_or_else(|| BuzzOutcome::NotFound(WsError::unknown_session()))
+}
+
+fn arbitrate(
+    actor: &mut SessionActor,
+    team_index: usize,
+    client_timestamp: Option<String>,
+    received_at: DateTime<Utc>,
+    offset_ms: Option<i64>,
+) -> BuzzOutcome {
+    let session = &mut actor.session;
+    let Some(team) = session.teams.get(team_index) else {
+        return BuzzOutcome::NotFound(WsError::invalid_team(team_index));
+    };
+    // Buzzing on the board is a buzzer check; it doesn't count as a buzz for a question.
+    let open = [GamePhase::Board, GamePhase::QuestionOpen, GamePhase::Answering];
+    if let Err(err) = phase::require(session, &open, "buzz")
+        .and_then(|()| early_buzz::check_lockout(session, team_index, received_at))
+    {
+        return BuzzOutcome::Rejected(err);
+    }
+    if team.has_buzzed
+        || team.buzz_lock_owned
+        || session.pending_buzzes.iter().any(|c| c.team_index == team_index)
+        || session.buzz_queue.iter().any(|e| e.team_index == team_index)
+    {
+        return BuzzOutcome::Rejected(WsError::new(
+            ErrorCode::LockHeld,
+            "This team has already buzzed",
+        ));
+    }
+
+    if daily_double::in_play(session) {
+        return BuzzOutcome::Rejected(WsError::new(
+            ErrorCode::LockHeld,
+            "Only the team that picked the daily double may answer",
+        ));
+    }
+
+    if early_buzz::awaiting_open(session) {
+        let (event, err) = early_buzz::lock_out(session, team_index, received_at);
+        actor.touch();
+        actor.broadcast(&event);
+        return BuzzOutcome::Rejected(err);
+    }
+
+    let pressed_at = corrected_press_time(client_timestamp.as_deref(), offset_ms, received_at);
+    if session.buzz_lock {
+        // Buzzers locked by the host (nobody holds the lock) don't queue.
+        if !session.teams.iter().any(|team| team.buzz_lock_owned) {
+            return BuzzOutcome::Rejected(WsError::new(ErrorCode::LockHeld, "Buzzers are locked"));
+        }
+        // Ranked by corrected press time, so a late packet can't cost a team its place.
+        let rank = session
+            .buzz_queue
+            .partition_point(|entry| entry.pressed_at <= pressed_at);
+        session.buzz_queue.insert(
+            rank,
+            BuzzQueueEntry {
+                team_index,
+                pressed_at,
+                answered: false,
+            },
+        );
+        let event = WsServerMsg::BuzzQueueUpdate {
+            queue: session.buzz_queue.clone(),
+        };
+        actor.touch();
+        actor.broadcast(&event);
+        return BuzzOutcome::Queued;
+    }
+
+    let opens_window = session.pending_buzzes.is_empty();
+    session.pending_buzzes.push(BuzzCandidate {
+        team_index,
+        client_timestamp: client_timestamp.unwrap_or_else(|| received_at.to_rfc3339()),
+        received_at,
+        pressed_at,
+        offset_ms,
+    });
+
+    // Sessions saved before the limit may hold a longer window.
+    let window_ms = session.buzz_window_ms.min(MAX_WINDOW_MS);
+    if window_ms == 0 {
+        // No window configured: the first buzz wins immediately.
+        let events = resolve(session);
+        commit(actor, &events);
+        return BuzzOutcome::Accepted;
+    }
+    if opens_window {
+        session.buzz_window_id += 1;
+        let window_id = session.buzz_window_id;
+        let state = actor.state.clone();
+        let session_id = actor.session_id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(window_ms.into())).await;
+            actor::call(&state, &session_id, move |actor| {
+                // The window was cleared and another opened since; its own timer closes it.
+                if actor.session.buzz_window_id != window_id {
+                    return;
+                }
+                let events = resolve(&mut actor.session);
+                commit(actor, &events);
+            })
+            .await;
+        });
+    }
+    BuzzOutcome::Accepted
+}
+
+/// Estimates when the button was pressed, in server time.
+fn corrected_press_time(
+    client_timestamp: Option<&str>,
+    offset_ms: Option<i64>,
+    received_at: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let (Some(client_timestamp), Some(offset_ms)) = (client_timestamp, offset_ms) else {
+        return received_at;
+    };
+    let Ok(client_time) = DateTime::parse_from_rfc3339(client_timestamp) else {
+        return received_at;
+    };
+    let corrected = client_time.with_timezone(&Utc) + ChronoDuration::milliseconds(offset_ms);
+    corrected.clamp(
+        received_at - ChronoDuration::milliseconds(MAX_COMPENSATION_MS),
+        received_at,
+    )
+}
+
+/// Awards the lock to the earliest corrected press in the window.
+///
+/// Returns no events if the window was emptied (e.g. buzzers were locked) meanwhile.
+fn resolve(session: &mut Session) -> Vec<WsServerMsg> {
+    let mut candidates = std::mem::take(&mut session.pending_buzzes);
+    if candidates.is_empty() || session.buzz_lock {
+        return Vec::new();
+    }
+    candidates.sort_by_key(|c| (c.pressed_at, c.received_at));
+
+    let winner = &candidates[0];
+    let locked = grant_lock(
+        session,
+        winner.team_index,
+        winner.received_at,
+        winner.client_timestamp.clone(),
+    );
+    session.buzz_queue = candidates
+        .iter()
+        .enumerate()
+        .map(|(rank, c)| BuzzQueueEntry {
+            team_index: c.team_index,
+            pressed_at: c.pressed_at,
+            answered: rank == 0,
+        })
+        .collect();
+
+    let results = candidates
+        .iter()
+        .map(|c| BuzzResult {
+            team_index: c.team_index,
+            team_name: session.teams[c.team_index].team_name.clone(),
+            client_timestamp: c.client_timestamp.clone(),
+            received_at: c.received_at,
+            pressed_at: c.pressed_at,
+            offset_ms: c.offset_ms,
+        })
+        .collect();
+    let mut events = timers::on_event(session, &locked);
+    events.insert(0, locked);
+    events.insert(1, WsServerMsg::BuzzResults { results });
+    events.insert(
+        2,
+        WsServerMsg::BuzzQueueUpdate {
+            queue: session.buzz_queue.clone(),
+        },
+    );
+    events
+}
+
+/// Gives the lock to a team and returns the `BuzzLocked` event. During a
+/// question the session moves on to `Answering`.
+pub(crate) fn grant_lock(
+    session: &mut Session,
+    team_index: usize,
+    server_timestamp: DateTime<Utc>,
+    client_timestamp: String,
+) -> WsServerMsg {
+    let on_board = session.phase == GamePhase::Board;
+    if session.phase == GamePhase::QuestionOpen {
+        session.phase = GamePhase::Answering;
+    }
+    let team = &mut session.teams[team_index];
+    team.buzz_lock_owned = true;
+    team.last_buzz_attempt = Some(server_timestamp);
+    if !on_board {
+        team.has_buzzed = true;
+    }
+    let team_name = team.team_name.clone();
+    session.buzz_lock = true;
+    WsServerMsg::BuzzLocked {
+        team_index,
+        server_timestamp,
+        client_timestamp,
+        team_name,
+    }
+}
+
+/// Hands the lock to the next queued team that hasn't answered yet, or
+/// releases buzzers if the queue is exhausted. Fails unless a team
+/// currently holds the lock.
+pub fn pass_to_next_in_queue(actor: &mut SessionActor) -> Result<(), WsError> {
+    let Some(events) = pass(&mut actor.session) else {
+        return Err(WsError::new(ErrorCode::InvalidState, "No team holds the lock"));
+    };
+    commit(actor, &events);
+    Ok(())
+}
+
+fn pass(session: &mut Session) -> Option<Vec<WsServerMsg>> {
+    let owner = session.teams.iter_mut().find(|team| team.buzz_lock_owned)?;
+    owner.buzz_lock_owned = false;
+
+    let Some(team_index) = next_in_queue(session) else {
+        session.release_buzz();
+        let mut events = timers::on_event(session, &WsServerMsg::BuzzReleased);
+        events.insert(0, WsServerMsg::BuzzReleased);
+        return Some(events);
+    };
+    let now = Utc::now();
+    let locked = grant_lock(session, team_index, now, now.to_rfc3339());
+    let mut events = timers::on_event(session, &locked);
+    events.insert(0, locked);
+    events.insert(
+        1,
+        WsServerMsg::BuzzQueueUpdate {
+            queue: session.buzz_queue.clone(),
+        },
+    );
+    Some(events)
+}
+
+/// Marks the next queued team that hasn't answered as answering and returns it.
+pub(crate) fn next_in_queue(session: &mut Session) -> Option<usize> {
+    let next = session.buzz_queue.iter_mut().find(|entry| !entry.answered)?;
+    next.answered = true;
+    Some(next.team_index)
+}
+
+/// Records and broadcasts the outcome of a resolved window.
+fn commit(actor: &mut SessionActor, events: &[WsServerMsg]) {
+    if events.is_empty() {
+        return;
+    }
+    timers::ensure_ticker(actor);
+    actor.touch();
+    for event in events {
+        actor.broadcast(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_700_000_000_000 + ms).unwrap()
+    }
+
+    fn question_open() -> Session {
+        let mut session = Session::new();
+        session.phase = GamePhase::QuestionOpen;
+        session
+    }
+
+    fn candidate(team_index: usize, received_ms: i64, pressed_ms: i64) -> BuzzCandidate {
+        BuzzCandidate {
+            team_index,
+            client_timestamp: at(received_ms).to_rfc3339(),
+            received_at: at(received_ms),
+            pressed_at: at(pressed_ms),
+            offset_ms: None,
+        }
+    }
+
+    fn lock_holder(events: &[WsServerMsg]) -> Option<usize> {
+        events.iter().find_map(|event| match event {
+            WsServerMsg::BuzzLocked { team_index, .. } => Some(*team_index),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn the_window_goes_to_the_earliest_corrected_press() {
+        let mut session = question_open();
+        // Team 2's packet arrived first, but team 0 pressed earlier.
+        session.pending_buzzes = vec![
+            candidate(2, 10, 10),
+            candidate(1, 30, 20),
+            candidate(0, 40, 5),
+        ];
+        let events = resolve(&mut session);
+        assert_eq!(lock_holder(&events), Some(0));
+        assert_eq!(session.phase, GamePhase::Answering);
+        assert!(session.buzz_lock && session.teams[0].buzz_lock_owned);
+        let queue: Vec<_> = session
+            .buzz_queue
+            .iter()
+            .map(|entry| (entry.team_index, entry.answered))
+            .collect();
+        assert_eq!(queue, [(0, true), (2, false), (1, false)]);
+        assert!(session.pending_buzzes.is_empty());
+
+        // A window emptied or locked meanwhile awards nothing.
+        assert!(resolve(&mut session).is_empty());
+    }
+
+    #[test]
+    fn press_times_are_corrected_within_the_compensation_cap() {
+        let received = at(1000);
+        let sent = at(900).to_rfc3339();
+        // The client's clock runs 50ms behind the server's.
+        assert_eq!(corrected_press_time(Some(&sent), Some(50), received), at(950));
+        // Never before the cap, never after arrival.
+        assert_eq!(corrected_press_time(Some(&sent), Some(-1000), received), at(500));
+        assert_eq!(corrected_press_time(Some(&sent), Some(5000), received), received);
+        // Without an offset or a readable timestamp, arrival time counts.
+        assert_eq!(corrected_press_time(Some(&sent), None, received), received);
+        assert_eq!(corrected_press_time(Some("soon"), Some(50), received), received);
+        assert_eq!(corrected_press_time(None, Some(50), received), received);
+    }
+
+    #[test]
+    fn passing_promotes_the_queue_in_order_then_releases() {
+        let mut session = question_open();
+        session.pending_buzzes = vec![
+            candidate(0, 0, 0),
+            candidate(1, 10, 10),
+            candidate(2, 20, 20),
+        ];
+        resolve(&mut session);
+
+        let events = pass(&mut session).unwrap();
+        assert_eq!(lock_holder(&events), Some(1));
+        assert!(!session.teams[0].buzz_lock_owned && session.teams[1].buzz_lock_owned);
+        let events = pass(&mut session).unwrap();
+        assert_eq!(lock_holder(&events), Some(2));
+
+        let events = pass(&mut session).unwrap();
+        assert!(matches!(events[0], WsServerMsg::BuzzReleased));
+        assert!(!session.buzz_lock && session.buzz_queue.is_empty());
+        // Nobody holds the lock now, so there is nothing to pass.
+        assert!(pass(&mut session).is_none());
+    }
+}
@@ -0,0 +1,179 @@
+//! Per-connection clock synchronisation for the Bible Challenge backend server.
+//!
+//! Works like NTP: a `Ping` carries the client send time (t0), the server notes
+//! when it received the ping (t1) and sent the `Pong` (t2), and the client
+//! reports when that pong arrived (t3) on its next ping. From those,
+//! `rtt = (t3 - t0) - (t2 - t1)` and `offset = ((t1 - t0) + (t2 - t3)) / 2`.
+//! A rolling window of samples is kept per connection; the offset from the
+//! lowest-RTT sample is used, since it has the least queuing error.
+//!
+//! Clients that never report pong receipt still get a rough offset from the
+//! smallest `t1 - t0` seen, which includes one-way latency.
+//!
+//! Each connection's estimates sit behind its own lock in `WsClient`, shared
+//! only by its connection task and its session's actor.
+
+use crate::models::{ConnectionStat, PongReceipt, WsClient};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// How many recent samples to keep per connection.
+const MAX_SAMPLES: usize = 16;
+
+/// A ping the server has answered and is waiting to hear back about.
+struct PendingPing {
+    client_timestamp: String,
+    client_sent: DateTime<Utc>,
+    server_received: DateTime<Utc>,
+    server_sent: DateTime<Utc>,
+}
+
+/// One completed four-timestamp exchange.
+#[derive(Clone, Copy)]
+struct ClockSample {
+    rtt_ms: i64,
+    offset_ms: i64,
+}
+
+/// Rolling clock estimates for one connection.
+#[derive(Default)]
+pub struct ConnectionClock {
+    samples: VecDeque<ClockSample>,
+    /// `server_received - client_sent` in milliseconds, for clients without pong receipts.
+    one_way: VecDeque<i64>,
+    pending: Option<PendingPing>,
+    last_seen: Option<DateTime<Utc>>,
+}
+
+fn push_bounded<T>(queue: &mut VecDeque<T>, value: T) {
+    if queue.len() == MAX_SAMPLES {
+        queue.pop_front();
+    }
+    queue.push_back(value);
+}
+
+fn parse_utc(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+impl ConnectionClock {
+    /// Milliseconds to add to a client timestamp to get server time.
+    pub fn offset_ms(&self) -> Option<i64> {
+        match self.samples.iter().min_by_key(|sample| sample.rtt_ms) {
+            Some(best) => Some(best.offset_ms),
+            None => self.one_way.iter().min().copied(),
+        }
+    }
+
+    /// Mean round-trip time over the current window.
+    pub fn rtt_ms(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let total: i64 = self.samples.iter().map(|sample| sample.rtt_ms).sum();
+        Some(total as f64 / self.samples.len() as f64)
+    }
+
+    /// Mean absolute deviation of RTT from its mean; a rough jitter measure.
+    pub fn jitter_ms(&self) -> Option<f64> {
+        let mean = self.rtt_ms()?;
+        let total: f64 = self
+            .samples
+            .iter()
+            .map(|sample| (sample.rtt_ms as f64 - mean).abs())
+            .sum();
+        Some(total / self.samples.len() as f64)
+    }
+
+    /// Completes the pending exchange if the client reported receiving its pong.
+    fn complete_pending(&mut self, receipt: &PongReceipt) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        if pending.client_timestamp != receipt.client_timestamp {
+            return;
+        }
+        let Some(client_received) = parse_utc(&receipt.received_at) else {
+            return;
+        };
+        let (t0, t1, t2, t3) = (
+            pending.client_sent,
+            pending.server_received,
+            pending.server_sent,
+            client_received,
+        );
+        let rtt_ms = (t3 - t0).num_milliseconds() - (t2 - t1).num_milliseconds();
+        if rtt_ms < 0 {
+            // Clock went backwards on one side; the sample is meaningless.
+            return;
+        }
+        let offset_ms = ((t1 - t0).num_milliseconds() + (t2 - t3).num_milliseconds()) / 2;
+        push_bounded(&mut self.samples, ClockSample { rtt_ms, offset_ms });
+    }
+
+    fn stat(&self, client: &WsClient) -> ConnectionStat {
+        ConnectionStat {
+            conn_id: client.conn_id,
+            rtt_ms: self.rtt_ms(),
+            jitter_ms: self.jitter_ms(),
+            offset_ms: self.offset_ms(),
+            samples: self.samples.len(),
+            last_seen: self.last_seen,
+            dropped_messages: 0,
+            queue_depth: 0,
+            max_queue_depth: 0,
+            queue_overflows: 0,
+        }
+    }
+}
+
+fn lock(clock: &Mutex<ConnectionClock>) -> MutexGuard<'_, ConnectionClock> {
+    // The estimates are only ever replaced whole, so a panicked holder can't
+    // leave them half-updated.
+    clock.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Records a ping and returns the server send time to put in the `Pong`.
+///
+/// `last_pong` completes the previous exchange. Malformed client timestamps
+/// are ignored for estimation but the ping is still answered.
+pub fn record_ping(
+    clock: &Mutex<ConnectionClock>,
+    client_timestamp: &str,
+    last_pong: Option<&PongReceipt>,
+    server_received: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let mut clock = lock(clock);
+    let server_sent = Utc::now();
+    clock.last_seen = Some(server_received);
+    if let Some(receipt) = last_pong {
+        clock.complete_pending(receipt);
+    }
+    clock.pending = None;
+    if let Some(client_sent) = parse_utc(client_timestamp) {
+        push_bounded(
+            &mut clock.one_way,
+            (server_received - client_sent).num_milliseconds(),
+        );
+        clock.pending = Some(PendingPing {
+            client_timestamp: client_timestamp.to_string(),
+            client_sent,
+            server_received,
+            server_sent,
+        });
+    }
+    server_sent
+}
+
+/// Returns the current offset estimate for a connection, if it has pinged.
+pub fn offset_ms(client: &WsClient) -> Option<i64> {
+    lock(&client.clock).offset_ms()
+}
+
+/// Returns a connection's clock stats.
+pub fn stat(client: &WsClient) -> ConnectionStat {
+    lock(&client.clock).stat(client)
+}
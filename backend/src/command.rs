@@ -0,0 +1,685 @@
+//! Session commands for the Bible Challenge backend server.
+//! Every change to a session is a `WsClientMsg`, whether it arrives over a
+//! WebSocket or through `POST /session/:id/commands`. `execute` checks the
+//! caller may send it, runs it on the session's actor, broadcasts what changed
+//! and reports how it landed, so both transports behave the same.
+
+use crate::actor::{self, SessionActor};
+use crate::buzz::{self, pass_to_next_in_queue, submit_buzz, BuzzOutcome};
+use crate::clock::{self, ConnectionClock};
+use crate::daily_double;
+use crate::early_buzz;
+use crate::final_round;
+use crate::judging;
+use crate::ledger;
+use crate::models::{
+    AckResult, AppState, CellPosition, CellState, ClientRole, ErrorCode, FinalQuestion, GamePhase,
+    ScoreActor, Session, Team, WsClient, WsClientMsg, WsError, WsServerMsg,
+};
+use crate::outbox;
+use crate::phase;
+use crate::rate_limit;
+use crate::rounds;
+use crate::seats;
+use crate::timers;
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Who sent a command.
+#[derive(Clone)]
+pub struct Caller {
+    pub role: ClientRole,
+    /// The WebSocket connection it arrived on; `None` over REST.
+    pub conn_id: Option<Uuid>,
+    /// The seat token a REST player presented in place of a connection.
+    pub seat_token: Option<String>,
+    /// The connection's clock estimates, for answering pings.
+    clock: Option<Arc<Mutex<ConnectionClock>>>,
+}
+
+impl Caller {
+    /// A REST request that presented the host token.
+    pub fn http_host() -> Self {
+        Self {
+            role: ClientRole::Host,
+            conn_id: None,
+            seat_token: None,
+            clock: None,
+        }
+    }
+
+    /// A REST request from a player that presented a seat token.
+    pub fn http_seat(seat_token: &str) -> Self {
+        Self {
+            role: ClientRole::Player,
+            conn_id: None,
+            seat_token: Some(seat_token.to_string()),
+            clock: None,
+        }
+    }
+
+    /// A message from a WebSocket connection.
+    pub fn connection(client: &WsClient) -> Self {
+        Self {
+            role: client.role,
+            conn_id: Some(client.conn_id),
+            seat_token: None,
+            clock: Some(client.clock.clone()),
+        }
+    }
+
+    /// Checks that a player is seated at `team_index`, by connection or seat
+    /// token; hosts may act for any team. Runs in the same actor job as the
+    /// change it guards, so a seat released in between can't slip through.
+    pub fn require_seat(&self, actor: &SessionActor, team_index: usize) -> Result<(), WsError> {
+        if self.role == ClientRole::Host {
+            return Ok(());
+        }
+        let seat = match (self.conn_id, &self.seat_token) {
+            (Some(conn_id), _) => seats::seat_of(actor, conn_id),
+            (None, Some(token)) => actor.session.seat_tokens.get(token).copied(),
+            (None, None) => None,
+        };
+        if seat == Some(team_index) {
+            Ok(())
+        } else {
+            Err(WsError::new(
+                ErrorCode::Unauthorized,
+                "Claim this team's seat first",
+            ))
+        }
+    }
+
+    fn score_actor(&self) -> ScoreActor {
+        match self.conn_id {
+            Some(conn_id) => ScoreActor::Connection { conn_id },
+            None => ScoreActor::Http,
+        }
+    }
+}
+
+/// How a command landed.
+pub struct Executed {
+    pub result: AckResult,
+    /// A reply meant only for the caller, e.g. `Pong` or `SeatClaimed`.
+    pub reply: Option<WsServerMsg>,
+}
+
+impl From<AckResult> for Executed {
+    fn from(result: AckResult) -> Self {
+        Self {
+            result,
+            reply: None,
+        }
+    }
+}
+
+impl Executed {
+    fn reply(reply: WsServerMsg) -> Self {
+        Self {
+            result: AckResult::Applied,
+            reply: Some(reply),
+        }
+    }
+}
+
+/// Runs one command for `caller` and reports how it landed. Resulting events
+/// are broadcast to the session before this returns; errors are for the
+/// caller only.
+pub async fn execute(
+    state: &Arc<AppState>,
+    session_id: &str,
+    caller: Caller,
+    msg: WsClientMsg,
+) -> Result<Executed, WsError> {
+    if msg.is_host_only() && caller.role != ClientRole::Host {
+        return Err(WsError::new(
+            ErrorCode::Unauthorized,
+            "Only the host can do that",
+        ));
+    }
+
+    // Pings and stats requests don't touch session state and are answered only to
+    // the caller. Seats, keep-alives and buzzes run their own jobs on the session actor.
+    let msg = match msg {
+        WsClientMsg::Ping { client_timestamp, last_pong } => {
+            let server_received = Utc::now();
+            // Only connections have a clock to sync; REST just gets the server time.
+            let server_sent = match &caller.clock {
+                Some(clock) => clock::record_ping(
+                    clock,
+                    &client_timestamp,
+                    last_pong.as_ref(),
+                    server_received,
+                ),
+                None => server_received,
+            };
+            return Ok(Executed::reply(WsServerMsg::Pong {
+                server_timestamp: server_sent,
+                server_received,
+                client_timestamp,
+            }));
+        }
+        WsClientMsg::GetConnectionStats => {
+            let (connections, dropped_messages) = actor::call(state, session_id, |actor| {
+                let connections = actor
+                    .clients
+                    .iter()
+                    .map(|client| {
+                        let mut stat = clock::stat(client);
+                        rate_limit::annotate_stats(client, &mut stat);
+                        outbox::annotate_stats(client, &mut stat);
+                        stat
+                    })
+                    .collect();
+                (connections, actor.rate_limit.dropped())
+            })
+            .await
+            .ok_or_else(WsError::unknown_session)?;
+            return Ok(Executed::reply(WsServerMsg::ConnectionStats {
+                connections,
+                dropped_messages,
+            }));
+        }
+        WsClientMsg::KeepAlive => {
+            actor::call(state, session_id, |actor| actor.keep_alive())
+                .await
+                .ok_or_else(WsError::unknown_session)?;
+            return Ok(AckResult::Applied.into());
+        }
+        WsClientMsg::ClaimSeat { team_index, seat_token } => {
+            let conn_id = caller.conn_id;
+            let (team_index, seat_token) = actor::call(state, session_id, move |actor| {
+                seats::claim_seat(actor, conn_id, team_index, seat_token)
+            })
+            .await
+            .ok_or_else(WsError::unknown_session)??;
+            return Ok(Executed::reply(WsServerMsg::SeatClaimed { team_index, seat_token }));
+        }
+        WsClientMsg::ReleaseSeat { team_index } => {
+            actor::call(state, session_id, move |actor| {
+                let event = seats::release_seat(actor, team_index)?;
+                actor.broadcast(&event);
+                Ok::<_, WsError>(())
+            })
+            .await
+            .ok_or_else(WsError::unknown_session)??;
+            return Ok(AckResult::Applied.into());
+        }
+        WsClientMsg::BuzzIn { team_index, client_timestamp } => {
+            // Without a connection there's no clock offset; REST buzzes are
+            // credited at their arrival time.
+            let client_timestamp = caller.conn_id.map(|_| client_timestamp);
+            let outcome =
+                submit_buzz(state, session_id, caller, team_index, client_timestamp).await;
+            return match outcome {
+                BuzzOutcome::Accepted => Ok(AckResult::Applied.into()),
+                BuzzOutcome::Queued => Ok(AckResult::Queued.into()),
+                BuzzOutcome::Rejected(err) | BuzzOutcome::NotFound(err) => Err(err),
+            };
+        }
+        WsClientMsg::PassToNextInQueue => {
+            actor::call(state, session_id, pass_to_next_in_queue)
+                .await
+                .ok_or_else(WsError::unknown_session)??;
+            return Ok(AckResult::Applied.into());
+        }
+        msg => msg,
+    };
+
+    // Players may only wager or answer for the seat they claimed.
+    let seat_team = msg.seat_team();
+    let score_actor = caller.score_actor();
+    actor::call(state, session_id, move |actor| {
+        if let Some(team_index) = seat_team {
+            caller.require_seat(actor, team_index)?;
+        }
+        let Some(event) = apply_client_msg(&mut actor.session, msg, score_actor)? else {
+            return Ok(AckResult::Unchanged.into());
+        };
+        let timer_events = timers::on_event(&mut actor.session, &event);
+        timers::ensure_ticker(actor);
+        actor.touch();
+        actor.broadcast(&event);
+        for timer_event in &timer_events {
+            actor.broadcast(timer_event);
+        }
+        if let WsServerMsg::TeamRemoved { team_index } = event {
+            seats::team_removed(actor, team_index);
+        }
+        Ok(AckResult::Applied.into())
+    })
+    .await
+    .ok_or_else(WsError::unknown_session)?
+}
+
+/// Applies a client message to the session and returns the event to broadcast,
+/// `Ok(None)` if it changed nothing, or the reason it was rejected.
+/// `actor` is recorded against any score change.
+fn apply_client_msg(
+    session: &mut Session,
+    msg: WsClientMsg,
+    actor: ScoreActor,
+) -> Result<Option<WsServerMsg>, WsError> {
+    match msg {
+        WsClientMsg::UpdateTeam { team_index, team } => {
+            if team_index >= session.teams.len() {
+                return Err(WsError::invalid_team(team_index));
+            }
+            ledger::set(session, team_index, team.score, actor)?;
+            session.teams[team_index] = team;
+            Ok(Some(WsServerMsg::FullState {
+                session: session.view(ClientRole::Host),
+            }))
+        }
+        WsClientMsg::ReleaseBuzz => {
+            session.release_buzz();
+            Ok(Some(WsServerMsg::BuzzReleased))
+        }
+        WsClientMsg::UpdateScore { team_index, score } => {
+            ledger::set(session, team_index, score, actor).map(Some)
+        }
+        WsClientMsg::UpdateTeamName { team_index, name } => {
+            let team = session
+                .teams
+                .get_mut(team_index)
+                .ok_or_else(|| WsError::invalid_team(team_index))?;
+            team.team_name = name.clone();
+            Ok(Some(WsServerMsg::TeamNameUpdate { team_index, name }))
+        }
+        WsClientMsg::LockBuzzers => {
+            session.lock_buzzers();
+            Ok(Some(WsServerMsg::BuzzersLocked))
+        }
+        WsClientMsg::UpdateDarkMode { enabled } => {
+            session.dark_mode = enabled;
+            Ok(Some(WsServerMsg::DarkModeUpdate { enabled }))
+        }
+        WsClientMsg::UpdateTimerEnabled { enabled } => {
+            session.timer_enabled = enabled;
+            Ok(Some(WsServerMsg::TimerEnabledUpdate { enabled }))
+        }
+        WsClientMsg::AddTeam => {
+            let new_team = Team::new(format!("Team {}", session.teams.len() + 1));
+            session.teams.push(new_team.clone());
+            Ok(Some(WsServerMsg::TeamAdded { team: new_team }))
+        }
+        WsClientMsg::RemoveTeam { team_index } => {
+            if team_index >= session.teams.len() {
+                return Err(WsError::invalid_team(team_index));
+            }
+            session.teams.remove(team_index);
+            session.pending_buzzes.clear();
+            session.buzz_queue.retain(|entry| entry.team_index != team_index);
+            for entry in &mut session.buzz_queue {
+                if entry.team_index > team_index {
+                    entry.team_index -= 1;
+                }
+            }
+            if let Some(board) = session.board.as_mut() {
+                board.shift_team_indices_after_removal(team_index);
+            }
+            session.remove_seats(team_index);
+            ledger::remove_team(session, team_index);
+            final_round::remove_team(session, team_index);
+            Ok(Some(WsServerMsg::TeamRemoved { team_index }))
+        }
+        WsClientMsg::JudgeCorrect { points } => judging::judge(session, true, points, actor).map(Some),
+        WsClientMsg::JudgeIncorrect { points } => {
+            judging::judge(session, false, points, actor).map(Some)
+        }
+        WsClientMsg::UpdateNegativeScoring { enabled } => {
+            session.negative_scoring = enabled;
+            Ok(Some(WsServerMsg::NegativeScoringUpdate { enabled }))
+        }
+        WsClientMsg::UpdateEarlyBuzzPenalty { penalty_ms } => {
+            session.early_buzz_penalty_ms = penalty_ms;
+            Ok(Some(WsServerMsg::EarlyBuzzPenaltyUpdate { penalty_ms }))
+        }
+        WsClientMsg::OpenBuzzers => early_buzz::open(session),
+        WsClientMsg::Undo => ledger::undo(session, actor).map(Some),
+        WsClientMsg::Redo => ledger::redo(session, actor).map(Some),
+        WsClientMsg::ResetHasBuzzed => {
+            session.reset_buzzers();
+            Ok(Some(WsServerMsg::HasBuzzedReset))
+        }
+        WsClientMsg::SetPhase { phase } => {
+            if let Some(how) = match phase {
+                GamePhase::Lobby => Some("start a new session"),
+                GamePhase::Answering => Some("have a team buzz in"),
+                GamePhase::Final => Some("send StartFinal"),
+                _ => None,
+            } {
+                return Err(WsError::new(
+                    ErrorCode::WrongPhase,
+                    format!("Can't set {:?} directly; {} instead", phase, how),
+                ));
+            }
+            // The host's question view reports QuestionOpen whatever the sub-phase.
+            if phase == session.phase || (phase == GamePhase::QuestionOpen && session.phase.is_question()) {
+                return Ok(None);
+            }
+            phase::transition(session, phase)?;
+            match phase {
+                GamePhase::Board => session.reset_buzzers(),
+                GamePhase::QuestionOpen => session.buzzers_open = false,
+                _ => {}
+            }
+            Ok(Some(WsServerMsg::PhaseChanged { phase }))
+        }
+        WsClientMsg::LoadGame { rounds } => Ok(Some(rounds::install(session, rounds))),
+        WsClientMsg::LoadBoard { mut board, daily_doubles } => {
+            // Replaces the live board; a finished game starts over with an upload instead.
+            phase::transition(session, GamePhase::Board)?;
+            session.reset_buzzers();
+            board.active_cell = None;
+            board.daily_double = None;
+            if let Some(count) = daily_doubles {
+                daily_double::assign_random(&mut board, count);
+            }
+            let public = board.view(ClientRole::Host);
+            session.board = Some(board);
+            Ok(Some(WsServerMsg::BoardLoaded { board: public }))
+        }
+        WsClientMsg::OpenCell { category, row, team_index } => {
+            // Cells open from the board, so the last question's buzzes and
+            // cell are always wrapped up first.
+            phase::require(session, &[GamePhase::Board], "open a cell")?;
+            let team_count = session.teams.len();
+            let board = session.board.as_mut().ok_or_else(no_board)?;
+            let pos = CellPosition { category, row };
+            let cell = board.cell_mut(pos).ok_or_else(|| {
+                WsError::new(
+                    ErrorCode::InvalidIndex,
+                    format!("No cell at category {}, row {}", category, row),
+                )
+            })?;
+            if cell.state == CellState::Done {
+                return Err(WsError::new(ErrorCode::InvalidState, "That cell is already done"));
+            }
+            let picked_by = match (cell.daily_double, team_index) {
+                (false, _) => None,
+                (true, Some(index)) if index < team_count => Some(index),
+                (true, Some(index)) => return Err(WsError::invalid_team(index)),
+                (true, None) => {
+                    return Err(WsError::new(
+                        ErrorCode::InvalidState,
+                        "Say which team picked this daily double",
+                    ));
+                }
+            };
+            if cell.state == CellState::Hidden {
+                cell.state = CellState::Open;
+            }
+            let question = cell.question.clone();
+            board.active_cell = Some(pos);
+            board.daily_double = None;
+            session.reset_buzzers();
+            phase::transition(session, GamePhase::QuestionOpen)?;
+            session.buzzers_open = false;
+            match picked_by {
+                Some(team_index) => Ok(Some(daily_double::start(session, pos, team_index))),
+                None => Ok(Some(WsServerMsg::CellOpened {
+                    category,
+                    row,
+                    question,
+                })),
+            }
+        }
+        WsClientMsg::SubmitWager { team_index, wager } => {
+            daily_double::place_wager(session, team_index, wager).map(Some)
+        }
+        WsClientMsg::StartFinal {
+            category,
+            question,
+            answer,
+            reference,
+        } => {
+            let content = match (category, question, answer) {
+                (Some(category), Some(question), Some(answer)) => FinalQuestion {
+                    category,
+                    question,
+                    answer,
+                    reference,
+                },
+                (None, None, None) => rounds::final_question(session).ok_or_else(|| {
+                    WsError::new(
+                        ErrorCode::InvalidState,
+                        "This round has no final question; send one",
+                    )
+                })?,
+                _ => {
+                    return Err(WsError::new(
+                        ErrorCode::ParseError,
+                        "Send category, question and answer together",
+                    ));
+                }
+            };
+            final_round::start(session, content).map(Some)
+        }
+        WsClientMsg::AdvanceRound => rounds::advance(session).map(Some),
+        WsClientMsg::SubmitFinalWager { team_index, wager } => {
+            final_round::submit_wager(session, team_index, wager).map(Some)
+        }
+        WsClientMsg::OpenFinalAnswers { duration_secs } => {
+            final_round::open_answers(session, duration_secs).map(Some)
+        }
+        WsClientMsg::SubmitFinalAnswer { team_index, answer } => {
+            final_round::submit_answer(session, team_index, answer).map(Some)
+        }
+        WsClientMsg::CloseFinalAnswers => Ok(final_round::close_answers(session)),
+        WsClientMsg::RevealFinalResponse { team_index } => {
+            final_round::reveal(session, team_index).map(Some)
+        }
+        WsClientMsg::JudgeFinal { team_index, correct } => {
+            final_round::judge(session, team_index, correct, actor).map(Some)
+        }
+        WsClientMsg::RevealAnswer => {
+            phase::check(session, GamePhase::Revealed)?;
+            let (pos, cell) = session
+                .board
+                .as_mut()
+                .ok_or_else(no_board)?
+                .active_cell_mut()
+                .ok_or_else(no_active_cell)?;
+            cell.state = CellState::Revealed;
+            session.phase = GamePhase::Revealed;
+            Ok(Some(WsServerMsg::AnswerRevealed {
+                category: pos.category,
+                row: pos.row,
+                answer: cell.answer.clone(),
+                reference: cell.reference.clone(),
+            }))
+        }
+        WsClientMsg::MarkDone { team_index } => {
+            if let Some(index) = team_index.filter(|index| *index >= session.teams.len()) {
+                return Err(WsError::invalid_team(index));
+            }
+            phase::check(session, GamePhase::Board)?;
+            let board = session.board.as_mut().ok_or_else(no_board)?;
+            let (pos, cell) = board.active_cell_mut().ok_or_else(no_active_cell)?;
+            cell.state = CellState::Done;
+            cell.answered_by = team_index;
+            board.active_cell = None;
+            board.daily_double = None;
+            session.phase = GamePhase::Board;
+            Ok(Some(WsServerMsg::CellDone {
+                category: pos.category,
+                row: pos.row,
+                team_index,
+            }))
+        }
+        WsClientMsg::StartTimer { kind, duration_secs } => {
+            Ok(Some(timers::start(session, kind, duration_secs)))
+        }
+        WsClientMsg::PauseTimer { kind } => Ok(timers::pause(session, kind)),
+        WsClientMsg::ResumeTimer { kind } => Ok(timers::resume(session, kind)),
+        WsClientMsg::CancelTimer { kind } => Ok(timers::cancel(session, kind)),
+        WsClientMsg::UpdateTimerDurations {
+            question_secs,
+            answer_secs,
+            final_secs,
+        } => {
+            if question_secs == 0 || answer_secs == 0 || final_secs == Some(0) {
+                return Err(WsError::new(
+                    ErrorCode::InvalidState,
+                    "Timers must run for at least 1 second",
+                ));
+            }
+            session.timers.question_secs = question_secs;
+            session.timers.answer_secs = answer_secs;
+            if let Some(final_secs) = final_secs {
+                session.timers.final_secs = final_secs;
+            }
+            Ok(Some(WsServerMsg::TimerDurationsUpdate {
+                question_secs,
+                answer_secs,
+                final_secs: session.timers.final_secs,
+            }))
+        }
+        WsClientMsg::UpdateBuzzWindow { window_ms } => {
+            if window_ms > buzz::MAX_WINDOW_MS {
+                return Err(WsError::new(
+                    ErrorCode::InvalidState,
+                    format!("The buzz window must be 0 to {} ms", buzz::MAX_WINDOW_MS),
+                ));
+            }
+            session.buzz_window_ms = window_ms;
+            Ok(Some(WsServerMsg::BuzzWindowUpdate { window_ms }))
+        }
+        // Handled separately in `execute`.
+        WsClientMsg::Ping { .. }
+        | WsClientMsg::GetConnectionStats
+        | WsClientMsg::KeepAlive
+        | WsClientMsg::ClaimSeat { .. }
+        | WsClientMsg::ReleaseSeat { .. }
+        | WsClientMsg::BuzzIn { .. }
+        | WsClientMsg::PassToNextInQueue => Ok(None),
+    }
+}
+
+fn no_board() -> WsError {
+    WsError::new(ErrorCode::InvalidState, "No board is loaded")
+}
+
+fn no_active_cell() -> WsError {
+    WsError::new(ErrorCode::InvalidState, "No cell is open")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::question_set::parse_question_set;
+
+    const GAME: &str = include_str!("../../frontend/examples/3_categories_3x3.tsv");
+
+    fn run(session: &mut Session, msg: WsClientMsg) -> Result<Option<WsServerMsg>, WsError> {
+        apply_client_msg(session, msg, ScoreActor::Http)
+    }
+
+    fn loaded_session() -> Session {
+        let set = parse_question_set(GAME).ok().unwrap();
+        let rounds = rounds::build(set, &rounds::parse_specs("Single:1").unwrap()).ok().unwrap();
+        let mut session = Session::new();
+        run(&mut session, WsClientMsg::LoadGame { rounds }).unwrap();
+        session
+    }
+
+    fn open_cell(category: usize, row: usize) -> WsClientMsg {
+        WsClientMsg::OpenCell { category, row, team_index: None }
+    }
+
+    #[test]
+    fn cells_only_open_from_the_board_with_fresh_buzzers() {
+        let mut session = loaded_session();
+        assert_eq!(session.phase, GamePhase::Board);
+        run(&mut session, open_cell(0, 0)).unwrap();
+        assert_eq!(session.phase, GamePhase::QuestionOpen);
+
+        // Team 0 holds the lock and team 1 buzzed behind it.
+        session.phase = GamePhase::Answering;
+        session.buzz_lock = true;
+        session.teams[0].buzz_lock_owned = true;
+        session.teams[0].has_buzzed = true;
+        session.teams[1].has_buzzed = true;
+        run(&mut session, WsClientMsg::RevealAnswer).unwrap();
+        assert_eq!(session.phase, GamePhase::Revealed);
+
+        let err = run(&mut session, open_cell(1, 0)).err().unwrap();
+        assert_eq!(err.code, ErrorCode::WrongPhase);
+        let active = session.board.as_ref().unwrap().active_cell;
+        assert_eq!(active, Some(CellPosition { category: 0, row: 0 }));
+
+        run(&mut session, WsClientMsg::MarkDone { team_index: Some(0) }).unwrap();
+        run(&mut session, open_cell(1, 0)).unwrap();
+        assert_eq!(session.phase, GamePhase::QuestionOpen);
+        assert!(!session.buzz_lock);
+        assert!(session.teams.iter().all(|team| !team.buzz_lock_owned && !team.has_buzzed));
+        let err = run(&mut session, open_cell(1, 1)).err().unwrap();
+        assert_eq!(err.code, ErrorCode::WrongPhase);
+    }
+
+    #[test]
+    fn buzz_windows_are_bounded() {
+        let mut session = Session::new();
+        let window = |window_ms| WsClientMsg::UpdateBuzzWindow { window_ms };
+        run(&mut session, window(buzz::MAX_WINDOW_MS)).unwrap();
+        let err = run(&mut session, window(buzz::MAX_WINDOW_MS + 1)).err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidState);
+        assert_eq!(session.buzz_window_ms, buzz::MAX_WINDOW_MS);
+    }
+
+    #[test]
+    fn timer_durations_must_be_positive() {
+        let mut session = Session::new();
+        let durations = |question_secs, final_secs| WsClientMsg::UpdateTimerDurations {
+            question_secs,
+            answer_secs: 10,
+            final_secs,
+        };
+        run(&mut session, durations(20, None)).unwrap();
+        for rejected in [durations(0, None), durations(20, Some(0))] {
+            let err = run(&mut session, rejected).err().unwrap();
+            assert_eq!(err.code, ErrorCode::InvalidState);
+        }
+        assert_eq!(session.timers.question_secs, 20);
+        assert_eq!(session.timers.answer_secs, 10);
+    }
+
+    #[test]
+    fn players_only_see_questions_and_answers_once_unveiled() {
+        let mut session = loaded_session();
+        let opened = run(&mut session, open_cell(0, 0)).unwrap();
+        let Some(WsServerMsg::CellOpened { question, .. }) = opened else {
+            panic!("expected CellOpened");
+        };
+        assert!(!question.is_empty());
+
+        let host = session.view(ClientRole::Host).board.unwrap();
+        let player = session.view(ClientRole::Player).board.unwrap();
+        let (open, hidden) = (&player.categories[0].cells[0], &player.categories[1].cells[0]);
+        assert_eq!(open.question, question);
+        assert!(open.answer.is_empty() && open.reference.is_empty());
+        assert!(hidden.question.is_empty() && hidden.answer.is_empty());
+        assert!(!host.categories[1].cells[0].answer.is_empty());
+
+        // Broadcasts are recorded once per role under the same seq.
+        let mut log = crate::history::EventLog::new();
+        let full = WsServerMsg::FullState { session: session.view(ClientRole::Host) };
+        let payloads = log.record(&full).unwrap();
+        let host_answer = &host.categories[1].cells[0].answer;
+        assert!(payloads.for_role(ClientRole::Host).contains(host_answer.as_str()));
+        assert!(!payloads.for_role(ClientRole::Player).contains(host_answer.as_str()));
+
+        session.phase = GamePhase::Answering;
+        let revealed = run(&mut session, WsClientMsg::RevealAnswer).unwrap();
+        let Some(WsServerMsg::AnswerRevealed { answer, .. }) = revealed else {
+            panic!("expected AnswerRevealed");
+        };
+        assert_eq!(answer, host.categories[0].cells[0].answer);
+        let player = session.view(ClientRole::Player).board.unwrap();
+        assert_eq!(player.categories[0].cells[0].answer, answer);
+    }
+}
@@ -0,0 +1,31 @@
+//! Environment configuration helpers for the Bible Challenge backend server.
+//! Every numeric setting is read the same way: unset means the default, and
+//! a value that doesn't parse is reported and replaced by the default.
+
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Reads a number from the environment variable `name`, or `default`.
+pub fn env_number<T: FromStr + Display>(name: &str, default: T) -> T {
+    env_number_where(name, default, |_| true)
+}
+
+/// Like `env_number`, but also falls back to `default` for values `valid`
+/// rejects.
+pub fn env_number_where<T: FromStr + Display>(
+    name: &str,
+    default: T,
+    valid: impl Fn(&T) -> bool,
+) -> T {
+    match env::var(name) {
+        Ok(text) => match text.trim().parse::<T>() {
+            Ok(value) if valid(&value) => value,
+            _ => {
+                eprintln!("Ignoring invalid {} `{}`; using {}", name, text, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
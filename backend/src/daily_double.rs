@@ -0,0 +1,111 @@
+//! Daily doubles for the Bible Challenge backend server.
+//! A daily double cell is flagged in the uploaded question set or picked at
+//! random when the board is loaded. Opening one locks the buzzers while the
+//! team that picked it wagers; the wager then replaces the cell's points when
+//! the answer is judged.
+
+use crate::buzz::grant_lock;
+use crate::models::{
+    Board, CellPosition, DailyDouble, ErrorCode, GamePhase, Session, WsError, WsServerMsg,
+};
+use crate::phase;
+use chrono::Utc;
+use rand::seq::index::sample;
+
+/// Flags `count` random cells as daily doubles, unless the board already has some.
+pub fn assign_random(board: &mut Board, count: usize) {
+    let mut cells: Vec<_> = board
+        .categories
+        .iter_mut()
+        .flat_map(|category| &mut category.cells)
+        .collect();
+    if cells.iter().any(|cell| cell.daily_double) {
+        return;
+    }
+    let count = count.min(cells.len());
+    for index in sample(&mut rand::rng(), cells.len(), count) {
+        cells[index].daily_double = true;
+    }
+}
+
+/// The most a team may wager: its score, or the board's top value if that's higher.
+pub fn max_wager(session: &Session, team_index: usize) -> i32 {
+    let board_max = session.board.as_ref().map_or(0, Board::max_points);
+    session.teams[team_index].score.max(board_max)
+}
+
+/// Starts the wager phase for a daily double picked by `team_index`.
+///
+/// The cell must already be the board's active cell.
+pub fn start(session: &mut Session, position: CellPosition, team_index: usize) -> WsServerMsg {
+    session.lock_buzzers();
+    let max_wager = max_wager(session, team_index);
+    if let Some(board) = session.board.as_mut() {
+        board.daily_double = Some(DailyDouble {
+            position,
+            team_index,
+            wager: None,
+        });
+    }
+    WsServerMsg::DailyDoubleRevealed {
+        category: position.category,
+        row: position.row,
+        team_index,
+        max_wager,
+    }
+}
+
+/// Records the picking team's wager and gives it the lock to answer.
+pub fn place_wager(
+    session: &mut Session,
+    team_index: usize,
+    wager: i32,
+) -> Result<WsServerMsg, WsError> {
+    phase::require(session, &[GamePhase::QuestionOpen], "wager")?;
+    let daily_double = session
+        .board
+        .as_ref()
+        .and_then(|board| board.daily_double)
+        .filter(|daily_double| daily_double.wager.is_none())
+        .ok_or_else(|| {
+            WsError::new(ErrorCode::InvalidState, "No daily double is waiting for a wager")
+        })?;
+    if team_index != daily_double.team_index {
+        return Err(WsError::new(
+            ErrorCode::Unauthorized,
+            "Only the team that picked the daily double may wager",
+        ));
+    }
+    let max_wager = max_wager(session, team_index);
+    if !(0..=max_wager).contains(&wager) {
+        return Err(WsError::new(
+            ErrorCode::InvalidWager,
+            format!("Wager must be between 0 and {}", max_wager),
+        ));
+    }
+
+    if let Some(daily_double) = session.board.as_mut().and_then(|board| board.daily_double.as_mut()) {
+        daily_double.wager = Some(wager);
+    }
+    let now = Utc::now();
+    grant_lock(session, team_index, now, now.to_rfc3339());
+    Ok(WsServerMsg::WagerPlaced { team_index, wager })
+}
+
+/// The wager `team_index` placed on the daily double in play, if any.
+pub fn wager_of(session: &Session, team_index: usize) -> Option<i32> {
+    session
+        .board
+        .as_ref()?
+        .daily_double
+        .filter(|daily_double| daily_double.team_index == team_index)?
+        .wager
+}
+
+/// Whether a daily double is in play, so only its team may answer.
+pub fn in_play(session: &Session) -> bool {
+    session
+        .board
+        .as_ref()
+        .is_some_and(|board| board.daily_double.is_some())
+}
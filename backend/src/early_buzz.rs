@@ -0,0 +1,71 @@
+//! Early-buzz penalties for the Bible Challenge backend server.
+//! With a penalty configured, each question opens with buzzers closed while
+//! the host reads it. A team that buzzes before the host sends `OpenBuzzers`
+//! is locked out for the penalty period. The lockout is kept on the `Team` and
+//! broadcast so buzzer pages can count it down.
+
+use crate::models::{ErrorCode, GamePhase, Session, WsError, WsServerMsg};
+use crate::phase;
+use chrono::{DateTime, Duration, Utc};
+
+/// Whether the open question is still waiting for the host to open buzzers.
+/// Always false when no penalty is configured.
+pub fn awaiting_open(session: &Session) -> bool {
+    session.early_buzz_penalty_ms > 0
+        && session.phase == GamePhase::QuestionOpen
+        && !session.buzzers_open
+}
+
+/// Opens buzzers for the current question. Returns `None` if they were
+/// already open (or no penalty is configured, so they never closed).
+pub fn open(session: &mut Session) -> Result<Option<WsServerMsg>, WsError> {
+    phase::require(session, &[GamePhase::QuestionOpen], "open buzzers")?;
+    if !awaiting_open(session) {
+        return Ok(None);
+    }
+    session.buzzers_open = true;
+    Ok(Some(WsServerMsg::BuzzersOpened))
+}
+
+/// Fails if `team_index` is still serving a lockout at `now`.
+pub fn check_lockout(
+    session: &Session,
+    team_index: usize,
+    now: DateTime<Utc>,
+) -> Result<(), WsError> {
+    match session.teams[team_index].locked_out_until {
+        Some(until) if until > now => Err(WsError::new(
+            ErrorCode::LockedOut,
+            format!(
+                "Locked out for another {:.1}s",
+                (until - now).num_milliseconds() as f64 / 1000.0
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Locks a team out for the session's penalty period and returns the event
+/// to broadcast along with the error for the buzzing client.
+pub fn lock_out(
+    session: &mut Session,
+    team_index: usize,
+    now: DateTime<Utc>,
+) -> (WsServerMsg, WsError) {
+    let penalty_ms = session.early_buzz_penalty_ms;
+    let until = now + Duration::milliseconds(penalty_ms.into());
+    session.teams[team_index].locked_out_until = Some(until);
+    let event = WsServerMsg::TeamLockedOut {
+        team_index,
+        until,
+        penalty_ms,
+    };
+    let err = WsError::new(
+        ErrorCode::LockedOut,
+        format!(
+            "Buzzed before buzzers opened; locked out for {:.1}s",
+            f64::from(penalty_ms) / 1000.0
+        ),
+    );
+    (event, err)
+}
@@ -0,0 +1,144 @@
+//! Session expiry for the Bible Challenge backend server.
+//! A sweep runs every `SESSION_SWEEP_SECS` and asks each session's actor
+//! whether the session has outlived its idle TTL or its maximum lifetime.
+//! Shortly before either deadline the actor sends `SessionExpiring` to its
+//! clients; once it passes, the session is closed like `POST /session/:id/close`
+//! and its snapshot removed. Changes, clients connecting or leaving, and
+//! `KeepAlive` restart the idle countdown, so connected clients are warned and
+//! can keep an idle session open. The warning is left out of the event log, and
+//! `SessionExpiryCancelled` withdraws it once activity moves the deadline.
+//!
+//! The sweep only ever talks to actors, one job each, so a busy session
+//! delays nothing but its own check.
+
+use crate::actor::{self, SessionActor};
+use crate::config::env_number;
+use crate::models::{AppState, ExpiryReason, Session, WsServerMsg};
+use crate::store::forget_session;
+use chrono::{DateTime, Duration, Utc};
+use futures_util::future::join_all;
+use std::sync::Arc;
+
+/// Idle TTL, lifetime cap and warning lead time for sessions.
+#[derive(Clone, Copy)]
+pub struct ExpiryConfig {
+    /// How long a session may go without changes or clients; `None` never.
+    pub idle_ttl: Option<Duration>,
+    /// How long a session may exist at all; `None` for no cap.
+    pub max_lifetime: Option<Duration>,
+    /// How long before expiry `SessionExpiring` is sent.
+    pub warning: Duration,
+    pub sweep_interval: std::time::Duration,
+}
+
+impl ExpiryConfig {
+    /// Reads `SESSION_IDLE_TTL_SECS` (default 1200), `SESSION_MAX_LIFETIME_SECS`
+    /// (default 0), `SESSION_EXPIRY_WARNING_SECS` (default 60) and
+    /// `SESSION_SWEEP_SECS` (default 30). A TTL or lifetime of 0 turns it off.
+    pub fn from_env() -> Self {
+        let optional = |secs: u64| (secs > 0).then(|| Duration::seconds(secs as i64));
+        Self {
+            idle_ttl: optional(env_number("SESSION_IDLE_TTL_SECS", 20 * 60)),
+            max_lifetime: optional(env_number("SESSION_MAX_LIFETIME_SECS", 0)),
+            warning: Duration::seconds(env_number("SESSION_EXPIRY_WARNING_SECS", 60) as i64),
+            sweep_interval: std::time::Duration::from_secs(
+                env_number("SESSION_SWEEP_SECS", 30).max(1),
+            ),
+        }
+    }
+}
+
+/// Sweeps for expired sessions forever, starting straight away.
+pub async fn run(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(state.expiry.sweep_interval);
+    loop {
+        interval.tick().await;
+        sweep(&state).await;
+    }
+}
+
+/// Warns sessions nearing expiry and closes the ones past it.
+async fn sweep(state: &AppState) {
+    let config = state.expiry;
+    let handles: Vec<_> = state
+        .sessions
+        .read()
+        .await
+        .iter()
+        .map(|(id, handle)| (id.clone(), handle.clone()))
+        .collect();
+
+    let checks = handles.into_iter().map(|(id, handle)| async move {
+        // An actor that no longer answers is gone; sweep its entry and snapshot.
+        let expired = handle
+            .call(move |actor| check(actor, config, Utc::now()))
+            .await;
+        (id, expired.unwrap_or(true))
+    });
+    for (id, expired) in join_all(checks).await {
+        if expired && actor::close(state, &id).await {
+            forget_session(state, &id).await;
+        }
+    }
+}
+
+/// Returns whether the session has expired, warning its clients first if
+/// expiry is near.
+fn check(actor: &mut SessionActor, config: ExpiryConfig, now: DateTime<Utc>) -> bool {
+    let Some((expires_at, reason)) = deadline(&actor.session, config) else {
+        return false;
+    };
+    if now >= expires_at {
+        return true;
+    }
+    if now >= expires_at - config.warning && actor.expiry_warned != Some(expires_at) {
+        actor.expiry_warned = Some(expires_at);
+        actor.notify(&WsServerMsg::SessionExpiring { expires_at, reason });
+    }
+    false
+}
+
+/// Withdraws the last `SessionExpiring` if the session's deadline has moved
+/// since, so clients stop counting down.
+pub fn cancel_stale_warning(actor: &mut SessionActor) {
+    let Some(warned) = actor.expiry_warned else {
+        return;
+    };
+    let expires_at = deadline(&actor.session, actor.state.expiry).map(|(at, _)| at);
+    if expires_at != Some(warned) {
+        actor.expiry_warned = None;
+        actor.notify(&WsServerMsg::SessionExpiryCancelled);
+    }
+}
+
+/// The earlier of the session's idle and lifetime deadlines, if either applies.
+fn deadline(session: &Session, config: ExpiryConfig) -> Option<(DateTime<Utc>, ExpiryReason)> {
+    let idle = config
+        .idle_ttl
+        .map(|ttl| (session.last_modified + ttl, ExpiryReason::Idle));
+    let lifetime = config
+        .max_lifetime
+        .map(|max| (session.created_at + max, ExpiryReason::MaxLifetime));
+    idle.into_iter().chain(lifetime).min_by_key(|(at, _)| *at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sweep_removes_sessions_whose_actor_is_gone() {
+        let state = AppState::new(None);
+        for id in ["LIVE", "DEAD"] {
+            let handle = actor::spawn(&state, id.to_string(), Session::new());
+            state.sessions.write().await.insert(id.to_string(), handle);
+        }
+        let dead = state.sessions.read().await["DEAD"].clone();
+        dead.stop(WsServerMsg::SessionClosed, false).await;
+
+        sweep(&state).await;
+        let sessions = state.sessions.read().await;
+        assert!(sessions.contains_key("LIVE"));
+        assert!(!sessions.contains_key("DEAD"));
+    }
+}
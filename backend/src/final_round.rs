@@ -0,0 +1,213 @@
+//! The final round for the Bible Challenge backend server.
+//! The host reveals a category and every team wagers in secret, then the
+//! question is shown and each team types an answer on its buzzer device until
+//! the final timer runs out. Wagers and answers are withheld from clients
+//! until the host reveals them one team at a time, and judging applies each
+//! wager to the team's score.
+
+use crate::ledger;
+use crate::models::{
+    ErrorCode, FinalEntry, FinalQuestion, FinalRound, FinalStage, GamePhase, ScoreActor,
+    ScoreReason, Session, WsError, WsServerMsg,
+};
+use crate::phase;
+
+/// Stages in which responses can be revealed and judged.
+const REVIEWING: &[FinalStage] = &[FinalStage::Reviewing, FinalStage::Finished];
+
+/// Starts (or restarts) the final round in the wagering stage.
+pub fn start(session: &mut Session, content: FinalQuestion) -> Result<WsServerMsg, WsError> {
+    phase::transition(session, GamePhase::Final)?;
+    session.lock_buzzers();
+    let category = content.category.clone();
+    session.final_round = Some(FinalRound {
+        content,
+        stage: FinalStage::Wagering,
+        entries: Vec::new(),
+    });
+    Ok(WsServerMsg::FinalStarted { category })
+}
+
+/// Records a team's hidden wager: anything from 0 up to its score.
+pub fn submit_wager(
+    session: &mut Session,
+    team_index: usize,
+    wager: i32,
+) -> Result<WsServerMsg, WsError> {
+    let score = session
+        .teams
+        .get(team_index)
+        .ok_or_else(|| WsError::invalid_team(team_index))?
+        .score;
+    let round = in_stage(session, &[FinalStage::Wagering], "Final wagers are closed")?;
+    let max_wager = score.max(0);
+    if !(0..=max_wager).contains(&wager) {
+        return Err(WsError::new(
+            ErrorCode::InvalidWager,
+            format!("Wager must be between 0 and {}", max_wager),
+        ));
+    }
+    let entry = entry_mut(round, team_index);
+    entry.wager = Some(wager);
+    entry.has_wager = true;
+    Ok(WsServerMsg::FinalWagerSubmitted { team_index })
+}
+
+/// Shows the question and opens answering. The final timer is started from
+/// the returned event by `timers::on_event`.
+pub fn open_answers(
+    session: &mut Session,
+    duration_secs: Option<u32>,
+) -> Result<WsServerMsg, WsError> {
+    let duration_secs = duration_secs.unwrap_or(session.timers.final_secs);
+    let round = in_stage(session, &[FinalStage::Wagering], "Final answers were already opened")?;
+    round.stage = FinalStage::Answering;
+    Ok(WsServerMsg::FinalQuestionRevealed {
+        question: round.content.question.clone(),
+        duration_secs,
+    })
+}
+
+/// Records a team's hidden answer. Teams may change it until answering closes.
+pub fn submit_answer(
+    session: &mut Session,
+    team_index: usize,
+    answer: String,
+) -> Result<WsServerMsg, WsError> {
+    if team_index >= session.teams.len() {
+        return Err(WsError::invalid_team(team_index));
+    }
+    let round = in_stage(session, &[FinalStage::Answering], "Final answers aren't open")?;
+    let entry = entry_mut(round, team_index);
+    entry.answer = Some(answer.trim().to_string());
+    entry.has_answer = true;
+    Ok(WsServerMsg::FinalAnswerSubmitted { team_index })
+}
+
+/// Closes answering, on host request or when the final timer runs out.
+/// Returns `None` if answering wasn't open.
+pub fn close_answers(session: &mut Session) -> Option<WsServerMsg> {
+    let round = session
+        .final_round
+        .as_mut()
+        .filter(|round| round.stage == FinalStage::Answering)?;
+    round.stage = FinalStage::Reviewing;
+    Some(WsServerMsg::FinalAnswersClosed {
+        answer: round.content.answer.clone(),
+        reference: round.content.reference.clone(),
+    })
+}
+
+/// Shows one team's wager and answer to everyone.
+pub fn reveal(session: &mut Session, team_index: usize) -> Result<WsServerMsg, WsError> {
+    let round = in_stage(session, REVIEWING, "Close final answers first")?;
+    let entry = find_mut(round, team_index)?;
+    entry.revealed = true;
+    Ok(WsServerMsg::FinalResponseRevealed {
+        team_index,
+        wager: entry.wager.unwrap_or(0),
+        answer: entry.answer.clone().unwrap_or_default(),
+    })
+}
+
+/// Judges a revealed response, adding or deducting its wager.
+pub fn judge(
+    session: &mut Session,
+    team_index: usize,
+    correct: bool,
+    actor: ScoreActor,
+) -> Result<WsServerMsg, WsError> {
+    let round = in_stage(session, REVIEWING, "Close final answers first")?;
+    let entry = find_mut(round, team_index)?;
+    if !entry.revealed {
+        return Err(WsError::new(ErrorCode::InvalidState, "Reveal this response first"));
+    }
+    if entry.correct.is_some() {
+        return Err(WsError::new(ErrorCode::InvalidState, "This response was already judged"));
+    }
+    entry.correct = Some(correct);
+    let wager = entry.wager.unwrap_or(0);
+    if round.entries.iter().all(|entry| entry.correct.is_some()) {
+        round.stage = FinalStage::Finished;
+    }
+    let stage = round.stage;
+
+    let (delta, reason) = match correct {
+        true => (wager, ScoreReason::Correct),
+        false => (-wager, ScoreReason::Incorrect),
+    };
+    let entry = match delta {
+        0 => None,
+        _ => Some(ledger::record(session, team_index, delta, reason, None, actor)?),
+    };
+    Ok(WsServerMsg::FinalJudged {
+        team_index,
+        correct,
+        score: session.teams[team_index].score,
+        entry,
+        stage,
+    })
+}
+
+/// Drops a removed team's response and shifts later teams down.
+pub fn remove_team(session: &mut Session, removed: usize) {
+    let Some(round) = session.final_round.as_mut() else {
+        return;
+    };
+    round.entries.retain(|entry| entry.team_index != removed);
+    for entry in &mut round.entries {
+        if entry.team_index > removed {
+            entry.team_index -= 1;
+        }
+    }
+}
+
+/// The final round, if the game is in it and it is in one of `stages`.
+fn in_stage<'a>(
+    session: &'a mut Session,
+    stages: &[FinalStage],
+    message: &str,
+) -> Result<&'a mut FinalRound, WsError> {
+    phase::require(session, &[GamePhase::Final], "play the final round")?;
+    let round = session
+        .final_round
+        .as_mut()
+        .ok_or_else(|| WsError::new(ErrorCode::InvalidState, "The final round hasn't started"))?;
+    if !stages.contains(&round.stage) {
+        return Err(WsError::new(ErrorCode::InvalidState, message));
+    }
+    Ok(round)
+}
+
+fn find_mut(round: &mut FinalRound, team_index: usize) -> Result<&mut FinalEntry, WsError> {
+    round
+        .entries
+        .iter_mut()
+        .find(|entry| entry.team_index == team_index)
+        .ok_or_else(|| {
+            WsError::new(
+                ErrorCode::InvalidIndex,
+                format!("Team {} has no final response", team_index),
+            )
+        })
+}
+
+/// The team's response, created on its first submission.
+fn entry_mut(round: &mut FinalRound, team_index: usize) -> &mut FinalEntry {
+    let position = match round.entries.iter().position(|entry| entry.team_index == team_index) {
+        Some(position) => position,
+        None => {
+            round.entries.push(FinalEntry {
+                team_index,
+                has_wager: false,
+                has_answer: false,
+                wager: None,
+                answer: None,
+                revealed: false,
+                correct: None,
+            });
+            round.entries.len() - 1
+        }
+    };
+    &mut round.entries[position]
+}
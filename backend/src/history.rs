@@ -0,0 +1,176 @@
+//! Sequenced broadcast history for the Bible Challenge backend server.
+//! Every broadcast gets the session's next `seq`, and the most recent
+//! broadcasts are kept in a bounded ring buffer. A reconnecting client passes
+//! `?since=<last seq it saw>` and is sent just the events it missed, or a
+//! `FullState` if they are no longer buffered.
+//!
+//! Sequence numbers start from the wall clock (in milliseconds) when a log is
+//! created, so numbers handed out before a restart are never mistaken for
+//! current ones.
+//!
+//! Broadcasts carrying a board are kept in two versions, the host's and the
+//! players' (see `WsServerMsg::for_players`), under the same `seq`.
+
+use crate::models::{ClientRole, WsServerMsg};
+use chrono::Utc;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// How many recent broadcasts to keep per session.
+const CAPACITY: usize = 512;
+
+/// A recorded broadcast, serialized for each role that sees it.
+#[derive(Clone)]
+pub struct Payloads {
+    host: String,
+    /// Only set when players get a different version.
+    players: Option<String>,
+}
+
+impl Payloads {
+    /// The payload to send a client with `role`.
+    pub fn for_role(&self, role: ClientRole) -> &str {
+        match (role, &self.players) {
+            (ClientRole::Player, Some(players)) => players,
+            _ => &self.host,
+        }
+    }
+}
+
+/// Recent broadcasts for one session, oldest first.
+pub struct EventLog {
+    last_seq: u64,
+    events: VecDeque<(u64, Payloads)>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self {
+            last_seq: Utc::now().timestamp_millis().max(0) as u64,
+            events: VecDeque::with_capacity(CAPACITY),
+        }
+    }
+
+    /// The `seq` of the latest broadcast; a `FullState` taken now reflects everything up to it.
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// Assigns the next `seq` to a broadcast and returns the payloads to send.
+    pub fn record(&mut self, msg: &WsServerMsg) -> Result<Payloads, serde_json::Error> {
+        let seq = self.last_seq + 1;
+        let payload = Payloads {
+            host: with_seq(msg, seq)?,
+            players: msg
+                .for_players()
+                .map(|players| with_seq(&players, seq))
+                .transpose()?,
+        };
+        self.last_seq = seq;
+        if self.events.len() == CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back((self.last_seq, payload.clone()));
+        Ok(payload)
+    }
+
+    /// The payloads broadcast after `since`, as a client with `role` gets
+    /// them, or `None` if some have already been dropped or `since` didn't
+    /// come from this log.
+    pub fn since(&self, since: u64, role: ClientRole) -> Option<Vec<String>> {
+        if since > self.last_seq {
+            return None;
+        }
+        let oldest = self
+            .events
+            .front()
+            .map_or(self.last_seq + 1, |(seq, _)| *seq);
+        if since + 1 < oldest {
+            return None;
+        }
+        Some(
+            self.events
+                .iter()
+                .filter(|(seq, _)| *seq > since)
+                .map(|(_, payload)| payload.for_role(role).to_string())
+                .collect(),
+        )
+    }
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serializes a server message with a top-level `seq` field.
+pub fn with_seq(msg: &WsServerMsg, seq: u64) -> Result<String, serde_json::Error> {
+    let mut value = serde_json::to_value(msg)?;
+    if let Value::Object(fields) = &mut value {
+        fields.insert("seq".to_string(), seq.into());
+    }
+    serde_json::to_string(&value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERFLOW: u64 = 10;
+
+    /// A log that has dropped its first `OVERFLOW` broadcasts, and the `seq`
+    /// it started from.
+    fn overflowed_log() -> (EventLog, u64) {
+        let mut log = EventLog::new();
+        let start = log.last_seq();
+        for _ in 0..CAPACITY as u64 + OVERFLOW {
+            log.record(&WsServerMsg::BuzzReleased).unwrap();
+        }
+        (log, start)
+    }
+
+    fn seqs(payloads: &[String]) -> Vec<u64> {
+        payloads
+            .iter()
+            .map(|payload| {
+                let value: Value = serde_json::from_str(payload).unwrap();
+                value["seq"].as_u64().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nothing_is_missed_when_caught_up() {
+        let (log, _) = overflowed_log();
+        assert_eq!(log.since(log.last_seq(), ClientRole::Host), Some(Vec::new()));
+    }
+
+    #[test]
+    fn replays_back_to_the_eviction_boundary() {
+        let (log, start) = overflowed_log();
+        let oldest = start + OVERFLOW + 1;
+
+        // Just before the boundary: everything still held is replayed, in order.
+        let missed = log.since(oldest - 1, ClientRole::Host).unwrap();
+        assert_eq!(seqs(&missed), (oldest..=log.last_seq()).collect::<Vec<_>>());
+
+        // Just after it: only what follows.
+        let missed = log.since(oldest, ClientRole::Player).unwrap();
+        assert_eq!(missed.len(), CAPACITY - 1);
+        assert_eq!(seqs(&missed)[0], oldest + 1);
+    }
+
+    #[test]
+    fn cannot_replay_past_the_eviction_boundary() {
+        let (log, start) = overflowed_log();
+        assert_eq!(log.since(start + OVERFLOW - 1, ClientRole::Host), None);
+        assert_eq!(log.since(0, ClientRole::Host), None);
+    }
+
+    #[test]
+    fn rejects_a_seq_from_the_future() {
+        let (log, _) = overflowed_log();
+        assert_eq!(log.since(log.last_seq() + 1, ClientRole::Host), None);
+    }
+}
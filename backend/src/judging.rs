@@ -0,0 +1,100 @@
+//! Answer judging for the Bible Challenge backend server.
+//! The host marks the team holding the buzz lock correct or incorrect; the
+//! server scores it against the open question, then closes the question or
+//! reopens buzzing for the teams still in play. A daily double is scored by its
+//! wager and closes either way.
+
+use crate::buzz::{grant_lock, next_in_queue};
+use crate::daily_double;
+use crate::ledger;
+use crate::models::{
+    CellState, ErrorCode, GamePhase, JudgeOutcome, ScoreActor, ScoreReason, Session, WsError, WsServerMsg,
+};
+use crate::phase;
+use chrono::Utc;
+
+/// Judges the team holding the lock and returns the `AnswerJudged` event.
+///
+/// `points` overrides the wager or the open cell's value and is required when
+/// neither is available.
+pub fn judge(
+    session: &mut Session,
+    correct: bool,
+    points: Option<i32>,
+    actor: ScoreActor,
+) -> Result<WsServerMsg, WsError> {
+    phase::require(session, &[GamePhase::Answering], "judge an answer")?;
+    let team_index = session
+        .teams
+        .iter()
+        .position(|team| team.buzz_lock_owned)
+        .ok_or_else(|| WsError::new(ErrorCode::InvalidState, "No team is answering"))?;
+    let question = session.board.as_ref().and_then(|board| board.active_cell);
+    let wager = daily_double::wager_of(session, team_index);
+    let points = points
+        .or(wager)
+        .or_else(|| {
+            let (_, cell) = session.board.as_mut()?.active_cell_mut()?;
+            Some(cell.points)
+        })
+        .ok_or_else(|| {
+            WsError::new(ErrorCode::InvalidState, "No question is open; send points to judge")
+        })?;
+
+    let (delta, reason) = match correct {
+        true => (points, ScoreReason::Correct),
+        false if wager.is_some() || session.negative_scoring => (
+            points.checked_neg().ok_or_else(ledger::out_of_range)?,
+            ScoreReason::Incorrect,
+        ),
+        false => (0, ScoreReason::Incorrect),
+    };
+    let entry = match delta {
+        0 => None,
+        _ => Some(ledger::record(session, team_index, delta, reason, question, actor)?),
+    };
+
+    session.teams[team_index].buzz_lock_owned = false;
+    let outcome = if correct {
+        close_question(session, Some(team_index))
+    } else if wager.is_some() {
+        close_question(session, None)
+    } else if let Some(next) = next_in_queue(session) {
+        let now = Utc::now();
+        grant_lock(session, next, now, now.to_rfc3339());
+        JudgeOutcome::PassedTo { team_index: next }
+    } else if session.teams.iter().any(|team| !team.has_buzzed) {
+        session.release_buzz();
+        JudgeOutcome::Reopened
+    } else {
+        close_question(session, None)
+    };
+
+    Ok(WsServerMsg::AnswerJudged {
+        team_index,
+        correct,
+        score: session.teams[team_index].score,
+        entry,
+        outcome,
+        buzz_queue: session.buzz_queue.clone(),
+    })
+}
+
+/// Marks the open cell done, resets buzzers and goes back to the board.
+fn close_question(session: &mut Session, answered_by: Option<usize>) -> JudgeOutcome {
+    let mut cell_position = None;
+    if let Some(board) = session.board.as_mut() {
+        if let Some((pos, cell)) = board.active_cell_mut() {
+            cell.state = CellState::Done;
+            cell.answered_by = answered_by;
+            cell_position = Some(pos);
+        }
+        board.active_cell = None;
+        board.daily_double = None;
+    }
+    session.reset_buzzers();
+    session.phase = GamePhase::Board;
+    JudgeOutcome::Closed {
+        cell: cell_position,
+    }
+}
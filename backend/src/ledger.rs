@@ -0,0 +1,330 @@
+//! Score ledger for the Bible Challenge backend server.
+//! Every score change is recorded as a delta with its reason, question, time
+//! and actor. The host can undo the most recent change and redo what was
+//! undone; both append compensating entries rather than editing history.
+
+use crate::models::{
+    CellPosition, ErrorCode, ScoreActor, ScoreEntry, ScoreReason, Session, WsError, WsServerMsg,
+};
+use chrono::Utc;
+
+/// Applies a score change, records it, and returns the `ScoreUpdate` to broadcast.
+pub fn apply(
+    session: &mut Session,
+    team_index: usize,
+    delta: i32,
+    reason: ScoreReason,
+    question: Option<CellPosition>,
+    actor: ScoreActor,
+) -> Result<WsServerMsg, WsError> {
+    let entry = record(session, team_index, delta, reason, question, actor)?;
+    Ok(score_update(session, entry))
+}
+
+/// Applies and records a score change as a new undoable step, for callers
+/// that broadcast it as part of their own event.
+pub fn record(
+    session: &mut Session,
+    team_index: usize,
+    delta: i32,
+    reason: ScoreReason,
+    question: Option<CellPosition>,
+    actor: ScoreActor,
+) -> Result<ScoreEntry, WsError> {
+    let entry = append(session, team_index, delta, reason, question, actor, None)?;
+    let ledger = &mut session.score_ledger;
+    ledger.undo_stack.push(entry.id);
+    ledger.redo_stack.clear();
+    Ok(entry)
+}
+
+/// Sets a team's score to an absolute value, recording the difference.
+pub fn set(
+    session: &mut Session,
+    team_index: usize,
+    score: i32,
+    actor: ScoreActor,
+) -> Result<WsServerMsg, WsError> {
+    let current = session
+        .teams
+        .get(team_index)
+        .ok_or_else(|| WsError::invalid_team(team_index))?
+        .score;
+    if score == current {
+        return Ok(WsServerMsg::ScoreUpdate {
+            team_index,
+            score,
+            entry: None,
+        });
+    }
+    let delta = score.checked_sub(current).ok_or_else(out_of_range)?;
+    apply(session, team_index, delta, ScoreReason::Manual, None, actor)
+}
+
+/// The error for a score change that doesn't fit in a score.
+pub fn out_of_range() -> WsError {
+    WsError::new(ErrorCode::InvalidState, "That score change is out of range")
+}
+
+/// Reverts the most recent score change that hasn't been undone.
+pub fn undo(session: &mut Session, actor: ScoreActor) -> Result<WsServerMsg, WsError> {
+    let id = session
+        .score_ledger
+        .undo_stack
+        .pop()
+        .ok_or_else(|| WsError::new(ErrorCode::InvalidState, "Nothing to undo"))?;
+    let original = find(session, id)?;
+    let (team_index, question) = (original.team_index, original.question);
+    let reverted = original.delta.checked_neg().ok_or_else(out_of_range).and_then(|delta| {
+        append(session, team_index, delta, ScoreReason::Undo, question, actor, Some(id))
+    });
+    let entry = match reverted {
+        Ok(entry) => entry,
+        Err(err) => {
+            // A step that can't be reverted stays where it was.
+            session.score_ledger.undo_stack.push(id);
+            return Err(err);
+        }
+    };
+    session.score_ledger.redo_stack.push(id);
+    Ok(score_update(session, entry))
+}
+
+/// Re-applies the most recently undone score change.
+pub fn redo(session: &mut Session, actor: ScoreActor) -> Result<WsServerMsg, WsError> {
+    let id = session
+        .score_ledger
+        .redo_stack
+        .pop()
+        .ok_or_else(|| WsError::new(ErrorCode::InvalidState, "Nothing to redo"))?;
+    let original = find(session, id)?;
+    let (team_index, question) = (original.team_index, original.question);
+    let entry = match append(
+        session,
+        team_index,
+        original.delta,
+        ScoreReason::Redo,
+        question,
+        actor,
+        Some(id),
+    ) {
+        Ok(entry) => entry,
+        Err(err) => {
+            // A step that can't be re-applied stays where it was.
+            session.score_ledger.redo_stack.push(id);
+            return Err(err);
+        }
+    };
+    session.score_ledger.undo_stack.push(id);
+    Ok(score_update(session, entry))
+}
+
+/// Drops a removed team's history and shifts later teams' entries down.
+pub fn remove_team(session: &mut Session, removed: usize) {
+    let ledger = &mut session.score_ledger;
+    ledger.entries.retain(|entry| entry.team_index != removed);
+    for entry in &mut ledger.entries {
+        if entry.team_index > removed {
+            entry.team_index -= 1;
+        }
+    }
+    let entries = &ledger.entries;
+    let exists = |id: &u64| entries.iter().any(|entry| entry.id == *id);
+    ledger.undo_stack.retain(exists);
+    ledger.redo_stack.retain(exists);
+}
+
+/// Looks up an entry by ID. The undo and redo stacks should only hold IDs of
+/// existing entries, but a hand-edited snapshot could break that.
+fn find(session: &Session, id: u64) -> Result<ScoreEntry, WsError> {
+    session
+        .score_ledger
+        .entries
+        .iter()
+        .find(|entry| entry.id == id)
+        .cloned()
+        .ok_or_else(|| {
+            WsError::new(
+                ErrorCode::InvalidState,
+                format!("Score entry {} no longer exists", id),
+            )
+        })
+}
+
+/// Adds `delta` to the team's score and appends the ledger entry.
+fn append(
+    session: &mut Session,
+    team_index: usize,
+    delta: i32,
+    reason: ScoreReason,
+    question: Option<CellPosition>,
+    actor: ScoreActor,
+    reverts: Option<u64>,
+) -> Result<ScoreEntry, WsError> {
+    let team = session
+        .teams
+        .get_mut(team_index)
+        .ok_or_else(|| WsError::invalid_team(team_index))?;
+    team.score = team.score.checked_add(delta).ok_or_else(out_of_range)?;
+    let ledger = &mut session.score_ledger;
+    ledger.last_id += 1;
+    let entry = ScoreEntry {
+        id: ledger.last_id,
+        team_index,
+        delta,
+        reason,
+        question,
+        timestamp: Utc::now(),
+        actor,
+        reverts,
+    };
+    ledger.entries.push(entry.clone());
+    Ok(entry)
+}
+
+fn score_update(session: &Session, entry: ScoreEntry) -> WsServerMsg {
+    WsServerMsg::ScoreUpdate {
+        team_index: entry.team_index,
+        score: session.teams[entry.team_index].score,
+        entry: Some(entry),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add(session: &mut Session, team_index: usize, delta: i32) {
+        apply(session, team_index, delta, ScoreReason::Manual, None, ScoreActor::Http).unwrap();
+    }
+
+    fn scores(session: &Session) -> Vec<i32> {
+        session.teams.iter().map(|team| team.score).collect()
+    }
+
+    fn error_code(result: Result<WsServerMsg, WsError>) -> ErrorCode {
+        result.err().unwrap().code
+    }
+
+    #[test]
+    fn undo_and_redo_walk_back_and_forth() {
+        let mut session = Session::new();
+        add(&mut session, 0, 100);
+        add(&mut session, 1, 200);
+        add(&mut session, 0, -50);
+        assert_eq!(scores(&session), [50, 200, 0]);
+
+        undo(&mut session, ScoreActor::Http).unwrap();
+        undo(&mut session, ScoreActor::Http).unwrap();
+        assert_eq!(scores(&session), [100, 0, 0]);
+        redo(&mut session, ScoreActor::Http).unwrap();
+        assert_eq!(scores(&session), [100, 200, 0]);
+        undo(&mut session, ScoreActor::Http).unwrap();
+        undo(&mut session, ScoreActor::Http).unwrap();
+        assert_eq!(scores(&session), [0, 0, 0]);
+        assert_eq!(error_code(undo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+
+        redo(&mut session, ScoreActor::Http).unwrap();
+        redo(&mut session, ScoreActor::Http).unwrap();
+        redo(&mut session, ScoreActor::Http).unwrap();
+        assert_eq!(scores(&session), [50, 200, 0]);
+        assert_eq!(error_code(redo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn undo_and_redo_append_entries_pointing_at_the_original() {
+        let mut session = Session::new();
+        add(&mut session, 2, 300);
+        let WsServerMsg::ScoreUpdate { entry: Some(undone), .. } =
+            undo(&mut session, ScoreActor::Http).unwrap()
+        else {
+            panic!("undo should record an entry");
+        };
+        assert_eq!(undone.delta, -300);
+        assert!(matches!(undone.reason, ScoreReason::Undo));
+        assert_eq!(undone.reverts, Some(1));
+
+        let WsServerMsg::ScoreUpdate { entry: Some(redone), score, .. } =
+            redo(&mut session, ScoreActor::Http).unwrap()
+        else {
+            panic!("redo should record an entry");
+        };
+        assert_eq!((redone.delta, redone.reverts, score), (300, Some(1), 300));
+        assert_eq!(session.score_ledger.entries.len(), 3);
+    }
+
+    #[test]
+    fn a_new_change_clears_redo() {
+        let mut session = Session::new();
+        add(&mut session, 0, 100);
+        add(&mut session, 0, 200);
+        undo(&mut session, ScoreActor::Http).unwrap();
+        add(&mut session, 1, 50);
+        assert_eq!(error_code(redo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+        assert_eq!(scores(&session), [100, 50, 0]);
+
+        // Undo now reverts the new change, then the one before the undone one.
+        undo(&mut session, ScoreActor::Http).unwrap();
+        undo(&mut session, ScoreActor::Http).unwrap();
+        assert_eq!(scores(&session), [0, 0, 0]);
+    }
+
+    #[test]
+    fn setting_the_same_score_records_nothing() {
+        let mut session = Session::new();
+        set(&mut session, 0, 0, ScoreActor::Http).unwrap();
+        assert!(session.score_ledger.entries.is_empty());
+        assert_eq!(error_code(undo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn removing_a_team_drops_its_history() {
+        let mut session = Session::new();
+        add(&mut session, 0, 100);
+        add(&mut session, 1, 200);
+        add(&mut session, 2, 300);
+        session.teams.remove(1);
+        remove_team(&mut session, 1);
+
+        undo(&mut session, ScoreActor::Http).unwrap();
+        assert_eq!(scores(&session), [100, 0]);
+        undo(&mut session, ScoreActor::Http).unwrap();
+        assert_eq!(scores(&session), [0, 0]);
+        assert_eq!(error_code(undo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+    }
+
+    #[test]
+    fn a_missing_entry_is_an_error_not_a_panic() {
+        let mut session = Session::new();
+        add(&mut session, 0, 100);
+        session.score_ledger.undo_stack.push(42);
+        session.score_ledger.redo_stack.push(43);
+        assert_eq!(error_code(undo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+        assert_eq!(error_code(redo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+        assert_eq!(scores(&session), [100, 0, 0]);
+    }
+
+    #[test]
+    fn overflowing_changes_are_rejected_without_touching_scores() {
+        let mut session = Session::new();
+        set(&mut session, 0, i32::MIN, ScoreActor::Http).unwrap();
+        let result = set(&mut session, 0, i32::MAX, ScoreActor::Http);
+        assert_eq!(error_code(result), ErrorCode::InvalidState);
+        assert_eq!(scores(&session), [i32::MIN, 0, 0]);
+        assert_eq!(session.score_ledger.entries.len(), 1);
+
+        // Undoing -2^31 would need +2^31; the step stays undoable.
+        assert_eq!(error_code(undo(&mut session, ScoreActor::Http)), ErrorCode::InvalidState);
+        assert_eq!(session.score_ledger.undo_stack.len(), 1);
+
+        add(&mut session, 1, i32::MAX);
+        let result = apply(&mut session, 1, 1, ScoreReason::Manual, None, ScoreActor::Http);
+        assert_eq!(error_code(result), ErrorCode::InvalidState);
+        assert_eq!(scores(&session), [i32::MIN, i32::MAX, 0]);
+
+        undo(&mut session, ScoreActor::Http).unwrap();
+        add(&mut session, 1, 1);
+        add(&mut session, 1, i32::MAX - 1);
+        assert_eq!(scores(&session), [i32::MIN, i32::MAX, 0]);
+    }
+}
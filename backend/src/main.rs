@@ -1,62 +1,90 @@
 //! Main entry point for the Bible Challenge backend server.
 //! Initializes the Axum application with HTTP routes, WebSocket support,
-//! shared state, CORS, and a background session cleanup task.
+//! shared state, CORS, session persistence, host authentication, a background
+//! session expiry sweep, and graceful shutdown on SIGINT/SIGTERM.
 
+mod actor;
+mod auth;
+mod buzz;
+mod clock;
+mod command;
+mod config;
+mod daily_double;
+mod early_buzz;
+mod expiry;
+mod final_round;
+mod history;
+mod judging;
+mod ledger;
 mod models;
+mod outbox;
+mod phase;
+mod question_set;
+mod rate_limit;
+mod rounds;
 mod routes;
-mod utils;
+mod seats;
+mod shutdown;
+mod store;
+mod timers;
 
-use axum::Router;
+use axum::{middleware, Router};
 use std::env;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
 use tower_http::cors::{Any, CorsLayer};
 
+use crate::auth::require_host_token;
 use crate::models::AppState;
 use crate::routes::{
     close_session, get_session_id, get_session_team_info, modify_session_team_info,
-    release_buzz_lock, set_buzz_lock_owned, start_session, ws_handler,
+    release_buzz_lock, run_command, set_buzz_lock_owned, start_session, upload_board, ws_handler,
 };
-use crate::utils::cleanup_sessions;
+use crate::store::{restore_sessions, store_from_env};
 
 #[tokio::main]
 async fn main() {
-    let state: Arc<AppState> = AppState::new();
+    let state: Arc<AppState> = AppState::new(store_from_env());
+    restore_sessions(&state).await;
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let app = Router::new()
-        .route("/session/start", axum::routing::post(start_session))
-        .route("/session/:id", axum::routing::get(get_session_id))
-        .route("/session/:id/teams", axum::routing::get(get_session_team_info))
+    // Routes that change session state require the host token.
+    let host_routes = Router::new()
         .route(
             "/session/:id/teams/:index",
             axum::routing::put(modify_session_team_info),
         )
         .route("/session/:id/close", axum::routing::post(close_session))
+        .route("/session/:id/board", axum::routing::post(upload_board))
+        .route("/session/:id/commands", axum::routing::post(run_command))
         .route(
             "/session/:id/buzz/release",
             axum::routing::post(release_buzz_lock),
         )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_host_token,
+        ));
+
+    let app = Router::new()
+        .route("/session/start", axum::routing::post(start_session))
+        .route("/session/:id", axum::routing::get(get_session_id))
+        .route("/session/:id/teams", axum::routing::get(get_session_team_info))
+        .route("/session/:id/ws", axum::routing::get(ws_handler))
+        // Takes the host token or a seat token; checked by the handler.
         .route(
             "/session/:id/buzz/:index",
             axum::routing::post(set_buzz_lock_owned),
         )
-        .route("/session/:id/ws", axum::routing::get(ws_handler))
+        .merge(host_routes)
         .layer(cors)
         .with_state(state.clone());
 
-    let cleanup_state = state.clone();
-    tokio::spawn(async move {
-        loop {
-            cleanup_sessions(&cleanup_state).await;
-            tokio::time::sleep(Duration::from_secs(600)).await;
-        }
-    });
+    tokio::spawn(expiry::run(state.clone()));
 
     let port = env::var("PORT")
         .unwrap_or_else(|_| "3000".to_string())
@@ -66,6 +94,7 @@ async fn main() {
     println!("Server running at http://{}", addr);
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(shutdown::on_signal(state.clone()))
         .await
         .unwrap();
 }
@@ -2,11 +2,20 @@
 //! This module defines the structures used for sessions, scores, questions,
 //! WebSocket messages, and shared application state.
 
+use crate::actor::SessionHandle;
+use crate::clock::ConnectionClock;
+use crate::expiry::ExpiryConfig;
+use crate::outbox::{Outbox, OutboxConfig};
+use crate::rate_limit::RateLimitConfig;
+use crate::shutdown::Shutdown;
+use crate::store::SessionStore;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::{Mutex as AsyncMutex, RwLock};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+use uuid::Uuid;
 
 /// Represents a team in the Bible Challenge.
 #[derive(Serialize, Deserialize, Clone)]
@@ -17,6 +26,394 @@ pub struct Team {
     pub has_buzzed: bool,
     #[serde(default)]
     pub last_buzz_attempt: Option<DateTime<Utc>>,
+    /// When the team's early-buzz lockout ends, if it was ever locked out.
+    #[serde(default)]
+    pub locked_out_until: Option<DateTime<Utc>>,
+}
+
+/// Lifecycle of a single board cell.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellState {
+    #[default]
+    Hidden,
+    Open,
+    Revealed,
+    Done,
+}
+
+/// A single question on the board.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BoardCell {
+    pub question: String,
+    pub answer: String,
+    #[serde(default)]
+    pub reference: String,
+    pub points: i32,
+    #[serde(default)]
+    pub state: CellState,
+    /// Index of the team credited with answering, once the cell is done.
+    #[serde(default)]
+    pub answered_by: Option<usize>,
+    /// Whether this is a daily double. Kept from clients until the cell is opened.
+    #[serde(default)]
+    pub daily_double: bool,
+}
+
+/// A board column: a category name and its cells, top to bottom.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BoardCategory {
+    pub name: String,
+    pub cells: Vec<BoardCell>,
+}
+
+/// Position of a cell on the board.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CellPosition {
+    pub category: usize,
+    pub row: usize,
+}
+
+/// The server-authoritative question board for a session.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Board {
+    pub categories: Vec<BoardCategory>,
+    /// The cell currently being played, if any.
+    #[serde(default)]
+    pub active_cell: Option<CellPosition>,
+    /// The daily double being played, if the active cell is one.
+    #[serde(default)]
+    pub daily_double: Option<DailyDouble>,
+}
+
+/// A daily double in play: the team that picked it and, once placed, its wager.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct DailyDouble {
+    pub position: CellPosition,
+    pub team_index: usize,
+    pub wager: Option<i32>,
+}
+
+impl Board {
+    pub fn cell_mut(&mut self, pos: CellPosition) -> Option<&mut BoardCell> {
+        self.categories.get_mut(pos.category)?.cells.get_mut(pos.row)
+    }
+
+    /// Returns the active cell along with its position.
+    pub fn active_cell_mut(&mut self) -> Option<(CellPosition, &mut BoardCell)> {
+        let pos = self.active_cell?;
+        self.cell_mut(pos).map(|cell| (pos, cell))
+    }
+
+    /// The highest point value on the board.
+    pub fn max_points(&self) -> i32 {
+        self.categories
+            .iter()
+            .flat_map(|category| &category.cells)
+            .map(|cell| cell.points)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// A copy safe to send to a client with `role`. Daily doubles stay secret
+    /// until opened; players also don't see a question before its cell is
+    /// opened, or its answer and reference before they are revealed.
+    pub fn view(&self, role: ClientRole) -> Board {
+        let mut board = self.clone();
+        let cells = board.categories.iter_mut().flat_map(|category| &mut category.cells);
+        for cell in cells {
+            if cell.state == CellState::Hidden {
+                cell.daily_double = false;
+            }
+            if role == ClientRole::Player {
+                match cell.state {
+                    CellState::Hidden => {
+                        cell.question.clear();
+                        cell.answer.clear();
+                        cell.reference.clear();
+                    }
+                    CellState::Open => {
+                        cell.answer.clear();
+                        cell.reference.clear();
+                    }
+                    CellState::Revealed | CellState::Done => {}
+                }
+            }
+        }
+        board
+    }
+
+    /// Keeps `answered_by` pointing at the right teams after a team is removed.
+    pub fn shift_team_indices_after_removal(&mut self, removed: usize) {
+        self.daily_double = self.daily_double.and_then(|mut daily_double| {
+            match daily_double.team_index {
+                index if index == removed => return None,
+                index if index > removed => daily_double.team_index -= 1,
+                _ => {}
+            }
+            Some(daily_double)
+        });
+        let cells = self.categories.iter_mut().flat_map(|category| &mut category.cells);
+        for cell in cells {
+            cell.answered_by = match cell.answered_by {
+                Some(index) if index == removed => None,
+                Some(index) if index > removed => Some(index - 1),
+                other => other,
+            };
+        }
+    }
+}
+
+/// The countdowns a session can run: the open question, the buzzed team's
+/// answer, and the final round's answer window.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimerKind {
+    Question,
+    Answer,
+    Final,
+}
+
+/// A single countdown. An expired timer stays in place with `remaining_secs == 0`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TimerState {
+    pub duration_secs: u32,
+    pub remaining_secs: u32,
+    pub running: bool,
+}
+
+/// Server-owned question and answer timers for a session.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SessionTimers {
+    pub question_secs: u32,
+    pub answer_secs: u32,
+    #[serde(default = "default_final_secs")]
+    pub final_secs: u32,
+    pub question: Option<TimerState>,
+    pub answer: Option<TimerState>,
+    #[serde(default)]
+    pub final_round: Option<TimerState>,
+    /// Whether a ticker task is currently driving this session's timers.
+    #[serde(skip)]
+    pub ticker_active: bool,
+}
+
+impl Default for SessionTimers {
+    fn default() -> Self {
+        Self {
+            question_secs: 30,
+            answer_secs: 15,
+            final_secs: default_final_secs(),
+            question: None,
+            answer: None,
+            final_round: None,
+            ticker_active: false,
+        }
+    }
+}
+
+fn default_final_secs() -> u32 {
+    30
+}
+
+impl SessionTimers {
+    pub fn get(&self, kind: TimerKind) -> Option<&TimerState> {
+        match kind {
+            TimerKind::Question => self.question.as_ref(),
+            TimerKind::Answer => self.answer.as_ref(),
+            TimerKind::Final => self.final_round.as_ref(),
+        }
+    }
+
+    pub fn slot_mut(&mut self, kind: TimerKind) -> &mut Option<TimerState> {
+        match kind {
+            TimerKind::Question => &mut self.question,
+            TimerKind::Answer => &mut self.answer,
+            TimerKind::Final => &mut self.final_round,
+        }
+    }
+
+    pub fn any_running(&self) -> bool {
+        [&self.question, &self.answer, &self.final_round]
+            .into_iter()
+            .flatten()
+            .any(|timer| timer.running)
+    }
+
+    pub fn is_expired(&self, kind: TimerKind) -> bool {
+        self.get(kind).is_some_and(|timer| timer.remaining_secs == 0)
+    }
+}
+
+/// Where the final round is up to.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FinalStage {
+    /// The category is shown and teams submit hidden wagers.
+    Wagering,
+    /// The question is shown and teams type answers until the final timer runs out.
+    Answering,
+    /// Answers are closed; the host reveals and judges each team's response.
+    Reviewing,
+    /// Every submitted response has been judged.
+    Finished,
+}
+
+/// One team's final round response. `wager` and `answer` are withheld from
+/// clients until the host reveals them.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FinalEntry {
+    pub team_index: usize,
+    pub has_wager: bool,
+    pub has_answer: bool,
+    pub wager: Option<i32>,
+    pub answer: Option<String>,
+    pub revealed: bool,
+    /// Set once the response is judged.
+    pub correct: Option<bool>,
+}
+
+/// The single question of a final round.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FinalQuestion {
+    pub category: String,
+    pub question: String,
+    pub answer: String,
+    #[serde(default)]
+    pub reference: String,
+}
+
+/// The end-of-game round: one question every team answers in writing.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FinalRound {
+    /// The question is withheld from clients until answering opens, and the
+    /// answer until answering closes.
+    #[serde(flatten)]
+    pub content: FinalQuestion,
+    pub stage: FinalStage,
+    /// Responses in the order teams first submitted.
+    pub entries: Vec<FinalEntry>,
+}
+
+impl FinalRound {
+    /// A copy safe to send to clients, with unrevealed secrets blanked.
+    pub fn public_view(&self) -> FinalRound {
+        let mut round = self.clone();
+        if round.stage == FinalStage::Wagering {
+            round.content.question.clear();
+        }
+        if matches!(round.stage, FinalStage::Wagering | FinalStage::Answering) {
+            round.content.answer.clear();
+            round.content.reference.clear();
+        }
+        for entry in round.entries.iter_mut().filter(|entry| !entry.revealed) {
+            entry.wager = None;
+            entry.answer = None;
+        }
+        round
+    }
+}
+
+/// One round of a multi-round game, with its own board and point multiplier.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GameRound {
+    pub name: String,
+    /// Already applied to the round's cell points.
+    pub multiplier: i32,
+    /// This round's board while another round is played. The live round's
+    /// board is `Session::board`.
+    #[serde(default)]
+    pub board: Option<Board>,
+    /// The question, if this is a final round. Never sent to clients.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub final_question: Option<FinalQuestion>,
+}
+
+impl GameRound {
+    pub fn is_final(&self) -> bool {
+        self.final_question.is_some()
+    }
+
+    /// A copy safe to send to a client with `role`.
+    pub fn view(&self, role: ClientRole) -> GameRound {
+        GameRound {
+            name: self.name.clone(),
+            multiplier: self.multiplier,
+            board: self.board.as_ref().map(|board| board.view(role)),
+            final_question: None,
+        }
+    }
+}
+
+/// A buzz waiting in the arbitration window.
+#[derive(Clone)]
+pub struct BuzzCandidate {
+    pub team_index: usize,
+    pub client_timestamp: String,
+    pub received_at: DateTime<Utc>,
+    /// Estimated press time in server time, after clock-offset correction.
+    pub pressed_at: DateTime<Utc>,
+    pub offset_ms: Option<i64>,
+}
+
+/// One team's buzz in a resolved arbitration window, as reported to clients.
+#[derive(Serialize, Clone)]
+pub struct BuzzResult {
+    pub team_index: usize,
+    pub team_name: String,
+    pub client_timestamp: String,
+    pub received_at: DateTime<Utc>,
+    pub pressed_at: DateTime<Utc>,
+    pub offset_ms: Option<i64>,
+}
+
+/// A team waiting its turn to answer, in buzz order.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BuzzQueueEntry {
+    pub team_index: usize,
+    pub pressed_at: DateTime<Utc>,
+    /// Whether this team has already held the lock for the current question.
+    pub answered: bool,
+}
+
+fn default_buzz_window_ms() -> u32 {
+    150
+}
+
+impl Team {
+    pub fn new(team_name: String) -> Self {
+        Self {
+            team_name,
+            score: 0,
+            buzz_lock_owned: false,
+            has_buzzed: false,
+            last_buzz_attempt: None,
+            locked_out_until: None,
+        }
+    }
+}
+
+/// Where a session is in the game. Transitions are validated by `phase`.
+///
+/// The aliases read sessions persisted with the old free-form `current_page`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GamePhase {
+    /// Session created; the game hasn't started.
+    #[default]
+    Lobby,
+    /// The board is shown and no question is open.
+    #[serde(alias = "home")]
+    Board,
+    /// A question is shown and buzzers are open (or locked by the host or timer).
+    #[serde(alias = "question")]
+    QuestionOpen,
+    /// A team holds the buzz lock and is answering.
+    Answering,
+    /// The answer to the open question is shown.
+    Revealed,
+    /// The final round is being played.
+    #[serde(alias = "final")]
+    Final,
+    /// The last round is over.
+    #[serde(alias = "finished")]
+    Finished,
 }
 
 /// Represents a session in the Bible Challenge.
@@ -26,9 +423,296 @@ pub struct Session {
     pub buzz_lock: bool,
     pub dark_mode: bool,
     pub timer_enabled: bool,
-    pub current_page: String,
+    #[serde(alias = "current_page", default)]
+    pub phase: GamePhase,
     pub created_at: DateTime<Utc>,
     pub last_modified: DateTime<Utc>,
+    #[serde(default)]
+    pub board: Option<Board>,
+    #[serde(default)]
+    pub timers: SessionTimers,
+    /// How long to collect buzzes before awarding the lock; 0 means first arrival wins.
+    #[serde(default = "default_buzz_window_ms")]
+    pub buzz_window_ms: u32,
+    /// Buzzes collected in the currently open arbitration window.
+    #[serde(skip)]
+    pub pending_buzzes: Vec<BuzzCandidate>,
+    /// Identifies the open arbitration window; bumped each time one opens, so a
+    /// window's timer can't close a later one.
+    #[serde(skip)]
+    pub buzz_window_id: u64,
+    /// Every team that buzzed since the lock was taken, earliest first.
+    #[serde(default)]
+    pub buzz_queue: Vec<BuzzQueueEntry>,
+    /// Secret required for host-only actions. Persisted, but never sent to clients.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub host_token: String,
+    /// Seat tokens handed to buzzer devices, mapped to their team. Never sent to clients.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub seat_tokens: HashMap<String, usize>,
+    /// Every score change, with undo/redo stacks.
+    #[serde(default)]
+    pub score_ledger: ScoreLedger,
+    /// Whether a wrong answer costs the question's points.
+    #[serde(default)]
+    pub negative_scoring: bool,
+    /// How long a team that buzzes before `OpenBuzzers` is locked out; 0
+    /// turns early-buzz penalties off and buzzers open with the question.
+    #[serde(default)]
+    pub early_buzz_penalty_ms: u32,
+    /// Whether the host has opened buzzers for the current question. Only
+    /// consulted when `early_buzz_penalty_ms` is set.
+    #[serde(default)]
+    pub buzzers_open: bool,
+    /// The final round, once the host starts it.
+    #[serde(default)]
+    pub final_round: Option<FinalRound>,
+    /// The rounds of an uploaded game, in order. Empty for a single loose board.
+    #[serde(default)]
+    pub rounds: Vec<GameRound>,
+    /// Index into `rounds` of the round being played.
+    #[serde(default)]
+    pub current_round: usize,
+}
+
+impl Session {
+    /// Creates a fresh session in the lobby with three default teams.
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            teams: (1..=3).map(|n| Team::new(format!("Team {}", n))).collect(),
+            buzz_lock: false,
+            dark_mode: false,
+            timer_enabled: false,
+            phase: GamePhase::Lobby,
+            created_at: now,
+            last_modified: now,
+            board: None,
+            timers: SessionTimers::default(),
+            buzz_window_ms: default_buzz_window_ms(),
+            pending_buzzes: Vec::new(),
+            buzz_window_id: 0,
+            buzz_queue: Vec::new(),
+            host_token: String::new(),
+            seat_tokens: HashMap::new(),
+            score_ledger: ScoreLedger::default(),
+            negative_scoring: false,
+            early_buzz_penalty_ms: 0,
+            buzzers_open: false,
+            final_round: None,
+            rounds: Vec::new(),
+            current_round: 0,
+        }
+    }
+
+    /// A copy safe to send to a client with `role`, with secrets removed.
+    pub fn view(&self, role: ClientRole) -> Box<Session> {
+        Box::new(Session {
+            host_token: String::new(),
+            seat_tokens: HashMap::new(),
+            board: self.board.as_ref().map(|board| board.view(role)),
+            final_round: self.final_round.as_ref().map(FinalRound::public_view),
+            rounds: self.rounds.iter().map(|round| round.view(role)).collect(),
+            ..self.clone()
+        })
+    }
+
+    /// Clears the buzz lock so any team that hasn't buzzed can buzz again.
+    pub fn release_buzz(&mut self) {
+        self.stop_answering();
+        self.buzz_lock = false;
+        for team in &mut self.teams {
+            team.buzz_lock_owned = false;
+        }
+        self.buzz_queue.clear();
+    }
+
+    /// Clears the lock and every team's buzz so the next question starts fresh.
+    pub fn reset_buzzers(&mut self) {
+        self.stop_answering();
+        self.buzz_lock = false;
+        for team in &mut self.teams {
+            team.buzz_lock_owned = false;
+            team.has_buzzed = false;
+        }
+        self.pending_buzzes.clear();
+        self.buzz_queue.clear();
+    }
+
+    /// Locks buzzers for everyone, e.g. when the question timer runs out.
+    pub fn lock_buzzers(&mut self) {
+        self.stop_answering();
+        self.buzz_lock = true;
+        for team in &mut self.teams {
+            team.buzz_lock_owned = false;
+            // Reset has_buzzed when timer expires
+            team.has_buzzed = false;
+        }
+        self.pending_buzzes.clear();
+        self.buzz_queue.clear();
+    }
+
+    /// Once nobody holds the lock, an answering question is open again.
+    fn stop_answering(&mut self) {
+        if self.phase == GamePhase::Answering {
+            self.phase = GamePhase::QuestionOpen;
+        }
+    }
+
+    /// Drops seat tokens for a removed team and shifts later seats down.
+    pub fn remove_seats(&mut self, removed: usize) {
+        self.seat_tokens.retain(|_, team_index| *team_index != removed);
+        for team_index in self.seat_tokens.values_mut() {
+            if *team_index > removed {
+                *team_index -= 1;
+            }
+        }
+    }
+}
+
+/// Why a team's score changed.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScoreReason {
+    /// The host set the score directly.
+    Manual,
+    /// The answering team was judged correct.
+    Correct,
+    /// The answering team was judged incorrect (with negative scoring on).
+    Incorrect,
+    /// Reverses the entry in `reverts`.
+    Undo,
+    /// Re-applies the entry in `reverts` after an undo.
+    Redo,
+}
+
+/// What happened to the question after judging.
+#[derive(Serialize, Clone)]
+#[serde(tag = "kind")]
+pub enum JudgeOutcome {
+    /// The question is over and buzzers are reset; `cell` (if any) is done.
+    Closed { cell: Option<CellPosition> },
+    /// The lock passed to the next team in the buzz queue.
+    PassedTo { team_index: usize },
+    /// Buzzers reopened for teams that haven't buzzed yet.
+    Reopened,
+}
+
+/// Who made a score change.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(tag = "kind")]
+pub enum ScoreActor {
+    /// A host WebSocket connection.
+    Connection { conn_id: Uuid },
+    /// A host-only HTTP route.
+    Http,
+}
+
+/// One change to a team's score. Entries are never edited; undo and redo
+/// append compensating entries.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScoreEntry {
+    pub id: u64,
+    pub team_index: usize,
+    pub delta: i32,
+    pub reason: ScoreReason,
+    /// The board cell the change was for, if any.
+    pub question: Option<CellPosition>,
+    pub timestamp: DateTime<Utc>,
+    pub actor: ScoreActor,
+    /// For undo/redo entries, the original entry being reverted or re-applied.
+    #[serde(default)]
+    pub reverts: Option<u64>,
+}
+
+/// Append-only score history with host undo/redo.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ScoreLedger {
+    pub entries: Vec<ScoreEntry>,
+    /// ID of the newest entry; IDs are never reused.
+    pub last_id: u64,
+    /// IDs of original entries that `Undo` can revert, most recent last.
+    pub undo_stack: Vec<u64>,
+    /// IDs of undone entries that `Redo` can re-apply, most recent last.
+    pub redo_stack: Vec<u64>,
+}
+
+/// A client's report of when it received a `Pong`, sent with its next `Ping`.
+#[derive(Deserialize)]
+pub struct PongReceipt {
+    /// The `client_timestamp` echoed in the pong being acknowledged.
+    pub client_timestamp: String,
+    pub received_at: String,
+}
+
+/// Clock-sync estimates and flood counters for one live connection, as reported to the host.
+#[derive(Serialize, Clone)]
+pub struct ConnectionStat {
+    pub conn_id: Uuid,
+    pub rtt_ms: Option<f64>,
+    pub jitter_ms: Option<f64>,
+    pub offset_ms: Option<i64>,
+    pub samples: usize,
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Messages from this connection dropped by the rate limiter.
+    pub dropped_messages: u64,
+    /// Messages waiting in the connection's send queue right now.
+    pub queue_depth: usize,
+    /// The deepest the send queue has been.
+    pub max_queue_depth: usize,
+    /// How often the send queue was full when a message arrived.
+    pub queue_overflows: u64,
+}
+
+/// Response body for `POST /session/start`.
+#[derive(Serialize)]
+pub struct StartSessionResponse {
+    pub session_id: String,
+    pub host_token: String,
+}
+
+/// Response body for `POST /session/:id/commands`.
+#[derive(Serialize)]
+pub struct CommandResponse {
+    /// What the WebSocket `Ack` for the command would carry.
+    pub result: AckResult,
+    /// A reply meant only for the sender, or the `Error` for a rejected command.
+    pub reply: Option<WsServerMsg>,
+}
+
+/// Number of live buzzer devices seated at a team.
+#[derive(Serialize, Clone)]
+pub struct TeamSeat {
+    pub team_index: usize,
+    pub devices: usize,
+}
+
+/// Why a session is about to expire.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExpiryReason {
+    /// Nothing has changed, connected, left or sent `KeepAlive` for the idle TTL.
+    Idle,
+    /// The session has reached its maximum lifetime.
+    MaxLifetime,
+}
+
+/// What a WebSocket connection is allowed to do.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClientRole {
+    /// Presented the host token; may send every message.
+    Host,
+    /// May only ping, claim a team seat, and buzz for that team.
+    Player,
+}
+
+/// A client message with its optional correlation ID. `request_id` sits
+/// alongside `type` in the same JSON object and is echoed back in the `Ack`.
+#[derive(Deserialize)]
+pub struct WsClientEnvelope {
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub msg: WsClientMsg,
 }
 
 /// Messages sent from client to server over WebSocket.
@@ -45,6 +729,11 @@ pub enum WsClientMsg {
         team_index: usize,
         score: i32,
     },
+    /// Replaces a team's full info, as `PUT /session/:id/teams/:index` does.
+    UpdateTeam {
+        team_index: usize,
+        team: Team,
+    },
     UpdateTeamName {
         team_index: usize,
         name: String,
@@ -60,12 +749,179 @@ pub enum WsClientMsg {
         team_index: usize,
     },
     ResetHasBuzzed,
-    SetPage {
-        page: String,
+    /// Judges the team holding the lock. `points` is only needed when no
+    /// board cell is open.
+    JudgeCorrect {
+        #[serde(default)]
+        points: Option<i32>,
+    },
+    JudgeIncorrect {
+        #[serde(default)]
+        points: Option<i32>,
+    },
+    UpdateNegativeScoring {
+        enabled: bool,
+    },
+    /// Sets the early-buzz lockout period; 0 turns penalties off.
+    UpdateEarlyBuzzPenalty {
+        penalty_ms: u32,
+    },
+    /// Opens buzzers once the host has finished reading the question. Only
+    /// needed when early-buzz penalties are on.
+    OpenBuzzers,
+    /// Reverts the most recent score change.
+    Undo,
+    /// Re-applies the most recently undone score change.
+    Redo,
+    /// Moves the game to another phase, e.g. `Board` when the host goes back
+    /// to the board or `QuestionOpen` when a question is shown. `Answering`
+    /// and `Final` are entered by buzzing and `StartFinal` instead.
+    SetPhase {
+        phase: GamePhase,
     },
     Ping {
         client_timestamp: String,
+        #[serde(default)]
+        last_pong: Option<PongReceipt>,
+    },
+    GetConnectionStats,
+    /// Restarts the session's idle countdown, e.g. in answer to `SessionExpiring`.
+    KeepAlive,
+    /// Claims a team seat for this connection. Send `seat_token` from an
+    /// earlier `SeatClaimed` to keep the same seat after reconnecting.
+    /// A connection keeps its seat until the host sends `ReleaseSeat`.
+    ClaimSeat {
+        #[serde(default)]
+        team_index: Option<usize>,
+        #[serde(default)]
+        seat_token: Option<String>,
     },
+    /// Frees a team's seat so its devices can claim a team again.
+    ReleaseSeat {
+        team_index: usize,
+    },
+    /// Installs a whole game, as `POST /session/:id/board` does after parsing
+    /// an upload, and makes its first round live. Allowed in any phase.
+    LoadGame {
+        rounds: Vec<GameRound>,
+    },
+    /// Loads a board as the live board and returns to the board. If none of
+    /// its cells is a daily double, `daily_doubles` cells are picked at random.
+    /// Not allowed once the game is `Finished`; upload a new game instead.
+    LoadBoard {
+        board: Board,
+        #[serde(default)]
+        daily_doubles: Option<usize>,
+    },
+    /// Opens a cell from the board, clearing the last question's buzzes. A
+    /// daily double needs the `team_index` that picked it.
+    OpenCell {
+        category: usize,
+        row: usize,
+        #[serde(default)]
+        team_index: Option<usize>,
+    },
+    /// The picking team's wager on the open daily double.
+    SubmitWager {
+        team_index: usize,
+        wager: i32,
+    },
+    /// Starts the final round by revealing its category. With no question
+    /// given, the current round's uploaded final question is used.
+    StartFinal {
+        #[serde(default)]
+        category: Option<String>,
+        #[serde(default)]
+        question: Option<String>,
+        #[serde(default)]
+        answer: Option<String>,
+        #[serde(default)]
+        reference: String,
+    },
+    /// Moves on to the next round of the game.
+    AdvanceRound,
+    /// A team's hidden final round wager, sent while wagering.
+    SubmitFinalWager {
+        team_index: usize,
+        wager: i32,
+    },
+    /// Shows the final question and starts the final timer.
+    OpenFinalAnswers {
+        #[serde(default)]
+        duration_secs: Option<u32>,
+    },
+    /// A team's hidden written answer, sent while the final timer runs.
+    SubmitFinalAnswer {
+        team_index: usize,
+        answer: String,
+    },
+    /// Closes answering before the final timer runs out.
+    CloseFinalAnswers,
+    /// Shows one team's final wager and answer to everyone.
+    RevealFinalResponse {
+        team_index: usize,
+    },
+    /// Judges a revealed final response and applies its wager.
+    JudgeFinal {
+        team_index: usize,
+        correct: bool,
+    },
+    RevealAnswer,
+    MarkDone {
+        team_index: Option<usize>,
+    },
+    StartTimer {
+        kind: TimerKind,
+        #[serde(default)]
+        duration_secs: Option<u32>,
+    },
+    PauseTimer {
+        kind: TimerKind,
+    },
+    ResumeTimer {
+        kind: TimerKind,
+    },
+    CancelTimer {
+        kind: TimerKind,
+    },
+    /// `final_secs` is left unchanged when omitted.
+    UpdateTimerDurations {
+        question_secs: u32,
+        answer_secs: u32,
+        #[serde(default)]
+        final_secs: Option<u32>,
+    },
+    UpdateBuzzWindow {
+        window_ms: u32,
+    },
+    PassToNextInQueue,
+}
+
+impl WsClientMsg {
+    /// Whether only a host connection may send this message.
+    pub fn is_host_only(&self) -> bool {
+        !matches!(
+            self,
+            WsClientMsg::BuzzIn { .. }
+                | WsClientMsg::Ping { .. }
+                | WsClientMsg::ClaimSeat { .. }
+                | WsClientMsg::KeepAlive
+                | WsClientMsg::SubmitWager { .. }
+                | WsClientMsg::SubmitFinalWager { .. }
+                | WsClientMsg::SubmitFinalAnswer { .. }
+        )
+    }
+
+    /// The team a player must be seated at to send this message, if any.
+    pub fn seat_team(&self) -> Option<usize> {
+        match self {
+            WsClientMsg::BuzzIn { team_index, .. }
+            | WsClientMsg::SubmitWager { team_index, .. }
+            | WsClientMsg::SubmitFinalWager { team_index, .. }
+            | WsClientMsg::SubmitFinalAnswer { team_index, .. } => Some(*team_index),
+            _ => None,
+        }
+    }
 }
 
 /// Messages sent from server to client over WebSocket.
@@ -73,7 +929,7 @@ pub enum WsClientMsg {
 #[serde(tag = "type")]
 pub enum WsServerMsg {
     FullState {
-        session: Session,
+        session: Box<Session>,
     },
     BuzzLocked {
         team_index: usize,
@@ -83,9 +939,21 @@ pub enum WsServerMsg {
     },
     BuzzersLocked,
     BuzzReleased,
+    /// The host opened buzzers for the current question.
+    BuzzersOpened,
+    /// A team buzzed before buzzers opened and can't buzz until `until`.
+    /// `penalty_ms` lets clients count down without relying on their clock.
+    TeamLockedOut {
+        team_index: usize,
+        until: DateTime<Utc>,
+        penalty_ms: u32,
+    },
     ScoreUpdate {
         team_index: usize,
         score: i32,
+        /// The ledger entry behind this change, if it was recorded.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        entry: Option<ScoreEntry>,
     },
     TeamNameUpdate {
         team_index: usize,
@@ -104,27 +972,345 @@ pub enum WsServerMsg {
         team_index: usize,
     },
     HasBuzzedReset,
-    PageUpdate {
-        page: String,
+    /// Going back to the `Board` also resets every team's buzz.
+    PhaseChanged {
+        phase: GamePhase,
     },
     SessionClosed,
+    /// The server is going down; reconnect after about `reconnect_after_ms`.
+    ServerShuttingDown {
+        reconnect_after_ms: u64,
+    },
+    /// The session will be closed at `expires_at` unless it sees activity
+    /// (for `Idle`) first. Sent to connected clients only; never replayed.
+    SessionExpiring {
+        expires_at: DateTime<Utc>,
+        reason: ExpiryReason,
+    },
+    /// Activity moved the deadline of the last `SessionExpiring`; it's off.
+    SessionExpiryCancelled,
+    /// Sent only to the pinging connection. `server_timestamp` is the send time.
     Pong {
         server_timestamp: DateTime<Utc>,
+        server_received: DateTime<Utc>,
         client_timestamp: String,
     },
+    /// Sent only to the claiming connection.
+    SeatClaimed {
+        team_index: usize,
+        seat_token: String,
+    },
+    /// A team's seat was released; devices seated there must claim again.
+    SeatReleased {
+        team_index: usize,
+    },
+    /// Sent only to hosts: teams with at least one live buzzer device.
+    SeatsUpdate {
+        seats: Vec<TeamSeat>,
+    },
+    /// Sent only to the requesting connection.
+    ConnectionStats {
+        connections: Vec<ConnectionStat>,
+        /// Messages dropped by the rate limiter across the whole session.
+        dropped_messages: u64,
+    },
+    BoardLoaded {
+        board: Board,
+    },
+    /// Carries the question, which players' boards leave out until now.
+    CellOpened {
+        category: usize,
+        row: usize,
+        question: String,
+    },
+    /// Carries the answer and reference, which players' boards leave out
+    /// until now.
+    AnswerRevealed {
+        category: usize,
+        row: usize,
+        answer: String,
+        reference: String,
+    },
+    /// Sent instead of `CellOpened` for a daily double. Buzzers stay locked
+    /// while `team_index` wagers up to `max_wager`.
+    DailyDoubleRevealed {
+        category: usize,
+        row: usize,
+        team_index: usize,
+        max_wager: i32,
+    },
+    /// The wager is in and the picking team holds the lock to answer.
+    WagerPlaced {
+        team_index: usize,
+        wager: i32,
+    },
+    /// A question set was uploaded as a game of `rounds`; the first round's
+    /// board is now live.
+    GameLoaded {
+        rounds: Vec<GameRound>,
+        board: Option<Board>,
+    },
+    /// The game moved on to `round`. `board` is `None` for a final round.
+    RoundStarted {
+        round: usize,
+        name: String,
+        multiplier: i32,
+        board: Option<Board>,
+        phase: GamePhase,
+    },
+    /// The last round is over.
+    GameFinished,
+    FinalStarted {
+        category: String,
+    },
+    /// A team's wager is in; the amount stays hidden.
+    FinalWagerSubmitted {
+        team_index: usize,
+    },
+    /// Answering is open for `duration_secs`.
+    FinalQuestionRevealed {
+        question: String,
+        duration_secs: u32,
+    },
+    /// A team's answer is in; the text stays hidden.
+    FinalAnswerSubmitted {
+        team_index: usize,
+    },
+    FinalAnswersClosed {
+        answer: String,
+        reference: String,
+    },
+    FinalResponseRevealed {
+        team_index: usize,
+        wager: i32,
+        answer: String,
+    },
+    FinalJudged {
+        team_index: usize,
+        correct: bool,
+        score: i32,
+        entry: Option<ScoreEntry>,
+        stage: FinalStage,
+    },
+    CellDone {
+        category: usize,
+        row: usize,
+        team_index: Option<usize>,
+    },
+    TimerStarted {
+        kind: TimerKind,
+        duration_secs: u32,
+    },
+    TimerTick {
+        kind: TimerKind,
+        remaining_secs: u32,
+    },
+    TimerPaused {
+        kind: TimerKind,
+        remaining_secs: u32,
+    },
+    TimerResumed {
+        kind: TimerKind,
+        remaining_secs: u32,
+    },
+    TimerCancelled {
+        kind: TimerKind,
+    },
+    TimerExpired {
+        kind: TimerKind,
+    },
+    TimerDurationsUpdate {
+        question_secs: u32,
+        answer_secs: u32,
+        final_secs: u32,
+    },
+    /// Every buzz from a resolved arbitration window, earliest press first.
+    BuzzResults {
+        results: Vec<BuzzResult>,
+    },
+    BuzzWindowUpdate {
+        window_ms: u32,
+    },
+    /// The buzz queue after a change. `BuzzReleased`, `BuzzersLocked` and
+    /// `HasBuzzedReset` also clear the queue without a separate update.
+    BuzzQueueUpdate {
+        queue: Vec<BuzzQueueEntry>,
+    },
+    /// The answering team was judged. Carries everything that changed so
+    /// clients don't need separate score, cell or buzzer events.
+    AnswerJudged {
+        team_index: usize,
+        correct: bool,
+        score: i32,
+        entry: Option<ScoreEntry>,
+        outcome: JudgeOutcome,
+        buzz_queue: Vec<BuzzQueueEntry>,
+    },
+    NegativeScoringUpdate {
+        enabled: bool,
+    },
+    EarlyBuzzPenaltyUpdate {
+        penalty_ms: u32,
+    },
+    /// Sent only to the originator of a message carrying a `request_id`,
+    /// after the message was applied or rejected.
+    Ack {
+        request_id: String,
+        result: AckResult,
+    },
+    /// Sent only to the connection whose message failed.
+    Error {
+        code: ErrorCode,
+        message: String,
+        request_id: Option<String>,
+    },
+}
+
+impl WsServerMsg {
+    /// The copy of a broadcast that players get, if it differs from the
+    /// host's: boards lose the questions and answers players can't see yet.
+    pub fn for_players(&self) -> Option<WsServerMsg> {
+        let player = ClientRole::Player;
+        let board_view = |board: &Option<Board>| board.as_ref().map(|board| board.view(player));
+        Some(match self {
+            WsServerMsg::FullState { session } => WsServerMsg::FullState {
+                session: session.view(player),
+            },
+            WsServerMsg::BoardLoaded { board } => WsServerMsg::BoardLoaded {
+                board: board.view(player),
+            },
+            WsServerMsg::GameLoaded { rounds, board } => WsServerMsg::GameLoaded {
+                rounds: rounds.iter().map(|round| round.view(player)).collect(),
+                board: board_view(board),
+            },
+            WsServerMsg::RoundStarted {
+                round,
+                name,
+                multiplier,
+                board,
+                phase,
+            } => WsServerMsg::RoundStarted {
+                round: *round,
+                name: name.clone(),
+                multiplier: *multiplier,
+                board: board_view(board),
+                phase: *phase,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// Stable error codes for `WsServerMsg::Error`. Clients should match on these,
+/// not on the message text.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorCode {
+    /// The message was not valid JSON or not a known message shape.
+    ParseError,
+    /// The session no longer exists.
+    UnknownSession,
+    /// A team, cell, or seat index is out of range.
+    InvalidIndex,
+    /// Buzzers are locked, or this team already buzzed for the question.
+    LockHeld,
+    /// The connection's role or seat doesn't allow this message.
+    Unauthorized,
+    /// The message doesn't apply right now, e.g. no board or no open cell.
+    InvalidState,
+    /// A daily double wager is outside the allowed range.
+    InvalidWager,
+    /// The message isn't allowed in the session's current game phase.
+    WrongPhase,
+    /// The team buzzed early and is serving its lockout.
+    LockedOut,
+    /// The connection or session is sending messages too fast.
+    RateLimited,
+}
+
+/// How a client message landed, reported in `WsServerMsg::Ack`.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(tag = "status")]
+pub enum AckResult {
+    /// The message took effect; any resulting events were broadcast first.
+    Applied,
+    /// The message was valid but changed nothing (e.g. pausing a paused timer).
+    Unchanged,
+    /// The buzz was added to the queue behind the team holding the lock.
+    Queued,
+    /// The message was rejected; the `Error` sent just before has details.
+    Rejected { code: ErrorCode },
+}
+
+/// A rejected client message, reported back as `WsServerMsg::Error`.
+#[derive(Debug)]
+pub struct WsError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl WsError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn unknown_session() -> Self {
+        Self::new(ErrorCode::UnknownSession, "Session not found")
+    }
+
+    pub fn invalid_team(team_index: usize) -> Self {
+        Self::new(ErrorCode::InvalidIndex, format!("No team at index {}", team_index))
+    }
+}
+
+/// A connected WebSocket client and the bounded queues feeding its socket.
+/// Queue with `outbox::deliver` (broadcasts) or `outbox::reply` so a full
+/// queue is handled.
+#[derive(Clone)]
+pub struct WsClient {
+    pub conn_id: Uuid,
+    pub tx: tokio::sync::mpsc::Sender<String>,
+    /// Replies for this connection alone; never coalesced into a `FullState`.
+    pub reply_tx: tokio::sync::mpsc::Sender<String>,
+    /// Backpressure state shared with the connection's send task.
+    pub outbox: Arc<Outbox>,
+    pub role: ClientRole,
+    /// The team seat this connection has claimed, if any.
+    pub team_index: Option<usize>,
+    /// Clock-sync estimates, updated by the connection task as it pings.
+    pub clock: Arc<Mutex<ConnectionClock>>,
+    /// Messages from this connection dropped by rate limiting.
+    pub dropped_messages: Arc<AtomicU64>,
 }
 
 /// Shared application state injected into route handlers via Axum's State extractor.
 pub struct AppState {
-    pub sessions: RwLock<HashMap<String, AsyncMutex<Session>>>,
-    pub ws_clients: RwLock<HashMap<String, Vec<tokio::sync::mpsc::UnboundedSender<String>>>>,
+    /// Each live session's actor, which owns the session and its clients.
+    /// Written only when a session starts or goes away.
+    pub sessions: RwLock<HashMap<String, SessionHandle>>,
+    /// Optional persistent store; `None` keeps sessions in memory only.
+    pub store: Option<Arc<dyn SessionStore>>,
+    /// Message limits for new connections and sessions.
+    pub rate_limit: RateLimitConfig,
+    /// Send queue size and slow-consumer policy for new connections.
+    pub outbox: OutboxConfig,
+    /// Idle TTL and lifetime cap enforced by the expiry sweep.
+    pub expiry: ExpiryConfig,
+    /// Graceful shutdown state; new sessions and connections are refused once started.
+    pub shutdown: Shutdown,
 }
 
 impl AppState {
-    pub fn new() -> Arc<Self> {
+    pub fn new(store: Option<Arc<dyn SessionStore>>) -> Arc<Self> {
         Arc::new(Self {
             sessions: RwLock::new(HashMap::new()),
-            ws_clients: RwLock::new(HashMap::new()),
+            store,
+            rate_limit: RateLimitConfig::from_env(),
+            outbox: OutboxConfig::from_env(),
+            expiry: ExpiryConfig::from_env(),
+            shutdown: Shutdown::from_env(),
         })
     }
 }
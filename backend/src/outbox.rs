@@ -0,0 +1,328 @@
+//! Bounded per-client send queues for the Bible Challenge backend server.
+//!
+//! Every WebSocket connection gets a queue of at most `WS_QUEUE_CAPACITY`
+//! outgoing broadcasts, drained by its send task. When a slow consumer (say, a
+//! phone on bad Wi-Fi) lets its queue fill up, `WS_SLOW_CONSUMER` decides:
+//!
+//! - `resync` (default): stop queuing for that client, throw away what is
+//!   queued once the socket moves again, and send one fresh `FullState`
+//!   instead, so the backlog coalesces into the current state.
+//! - `disconnect`: drop the broadcasts that don't fit; the client sees the
+//!   gap in `seq` and reconnects with `?since=`.
+//!
+//! Either way a client whose queue overflows `WS_SLOW_CONSUMER_LIMIT` times
+//! is disconnected.
+//!
+//! Replies meant for one connection (`Ack`, `Error`, `Pong`, `SeatClaimed`,
+//! ...) go through a second queue of the same size that is never coalesced,
+//! since a `FullState` can't stand in for them. A client that lets that queue
+//! fill up is disconnected.
+//!
+//! Queue depth, the deepest the queue has been, and overflow counts are
+//! reported per connection in `ConnectionStats`.
+
+use crate::actor;
+use crate::config::{env_number, env_number_where};
+use crate::history;
+use crate::models::{AppState, ClientRole, ConnectionStat, WsClient, WsServerMsg};
+use std::env;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use uuid::Uuid;
+
+/// Used when `WS_QUEUE_CAPACITY` is unset or invalid.
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Used when `WS_SLOW_CONSUMER_LIMIT` is unset or invalid.
+const DEFAULT_DISCONNECT_AFTER: u64 = 5;
+
+/// What to do with a client whose queue is full.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlowConsumerPolicy {
+    /// Skip messages until the client catches up, then send a fresh `FullState`.
+    Resync,
+    /// Drop the messages that don't fit.
+    Disconnect,
+}
+
+/// Queue size and slow-consumer policy for every connection.
+#[derive(Clone, Copy)]
+pub struct OutboxConfig {
+    pub capacity: usize,
+    pub policy: SlowConsumerPolicy,
+    /// How many overflows a client may have before it is disconnected; 0
+    /// never disconnects it.
+    pub disconnect_after: u64,
+}
+
+impl OutboxConfig {
+    /// Reads `WS_QUEUE_CAPACITY`, `WS_SLOW_CONSUMER` (`resync` or `disconnect`)
+    /// and `WS_SLOW_CONSUMER_LIMIT` (default 5).
+    pub fn from_env() -> Self {
+        let capacity = env_number_where("WS_QUEUE_CAPACITY", DEFAULT_CAPACITY, |n| *n > 0);
+        let policy = match env::var("WS_SLOW_CONSUMER") {
+            Ok(text) if text.eq_ignore_ascii_case("disconnect") => SlowConsumerPolicy::Disconnect,
+            Ok(text) if !text.eq_ignore_ascii_case("resync") => {
+                eprintln!("Ignoring invalid WS_SLOW_CONSUMER `{}`", text);
+                SlowConsumerPolicy::Resync
+            }
+            _ => SlowConsumerPolicy::Resync,
+        };
+        let disconnect_after = env_number("WS_SLOW_CONSUMER_LIMIT", DEFAULT_DISCONNECT_AFTER);
+        Self {
+            capacity,
+            policy,
+            disconnect_after,
+        }
+    }
+}
+
+/// Backpressure state shared between a client's senders and its send task.
+pub struct Outbox {
+    policy: SlowConsumerPolicy,
+    disconnect_after: u64,
+    /// Set when the queue overflowed under `Resync`; nothing is queued until
+    /// the send task has sent a fresh `FullState`.
+    stale: AtomicBool,
+    /// Set once the client is being disconnected as a slow consumer.
+    closing: AtomicBool,
+    /// Wakes the send task to drop the connection.
+    kicked: Notify,
+    max_depth: AtomicUsize,
+    overflows: AtomicU64,
+}
+
+impl Outbox {
+    /// Whether the send task owes the client a fresh `FullState`.
+    pub fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::Acquire)
+    }
+
+    /// Resolves once the connection should be dropped as a slow consumer.
+    pub async fn kicked(&self) {
+        self.kicked.notified().await
+    }
+}
+
+/// The receiving ends of a client's queues, drained by its send task.
+pub struct Inbox {
+    pub broadcasts: mpsc::Receiver<String>,
+    pub replies: mpsc::Receiver<String>,
+}
+
+/// Creates a client's bounded queues and their shared backpressure state,
+/// returning the client and the inbox its send task drains.
+pub fn client(config: OutboxConfig, role: ClientRole) -> (WsClient, Inbox) {
+    let (tx, broadcasts) = mpsc::channel(config.capacity);
+    let (reply_tx, replies) = mpsc::channel(config.capacity);
+    let outbox = Arc::new(Outbox {
+        policy: config.policy,
+        disconnect_after: config.disconnect_after,
+        stale: AtomicBool::new(false),
+        closing: AtomicBool::new(false),
+        kicked: Notify::new(),
+        max_depth: AtomicUsize::new(0),
+        overflows: AtomicU64::new(0),
+    });
+    let client = WsClient {
+        conn_id: Uuid::new_v4(),
+        tx,
+        reply_tx,
+        outbox,
+        role,
+        team_index: None,
+        clock: Arc::default(),
+        dropped_messages: Arc::default(),
+    };
+    (
+        client,
+        Inbox {
+            broadcasts,
+            replies,
+        },
+    )
+}
+
+/// Queues a broadcast for a client without waiting. Returns false if the
+/// client should be dropped from its session (its connection is gone, or it
+/// is being disconnected as a slow consumer).
+pub fn deliver(client: &WsClient, payload: String) -> bool {
+    let outbox = &client.outbox;
+    if outbox.closing.load(Ordering::Acquire) {
+        return false;
+    }
+    if outbox.is_stale() {
+        // A FullState is on its way and will cover this message.
+        return true;
+    }
+    match client.tx.try_send(payload) {
+        Ok(()) => {
+            let depth = client.tx.max_capacity() - client.tx.capacity();
+            outbox.max_depth.fetch_max(depth, Ordering::Relaxed);
+            true
+        }
+        Err(TrySendError::Full(_)) => {
+            let overflows = outbox.overflows.fetch_add(1, Ordering::Relaxed) + 1;
+            let limit = outbox.disconnect_after;
+            if limit > 0 && overflows >= limit {
+                kick(client, &format!("its queue overflowed {} times", overflows));
+                return false;
+            }
+            if outbox.policy == SlowConsumerPolicy::Resync {
+                eprintln!(
+                    "Client {} fell behind; resyncing it with FullState",
+                    client.conn_id
+                );
+                outbox.stale.store(true, Ordering::Release);
+            }
+            true
+        }
+        Err(TrySendError::Closed(_)) => false,
+    }
+}
+
+/// Queues a reply meant for this client alone. Replies are never coalesced
+/// or dropped; a client whose reply queue is full is disconnected.
+pub fn reply(client: &WsClient, payload: String) {
+    if client.outbox.closing.load(Ordering::Acquire) {
+        return;
+    }
+    if let Err(TrySendError::Full(_)) = client.reply_tx.try_send(payload) {
+        client.outbox.overflows.fetch_add(1, Ordering::Relaxed);
+        kick(client, "its reply queue is full");
+    }
+}
+
+/// Marks a client as disconnecting and wakes its send task to drop it.
+fn kick(client: &WsClient, why: &str) {
+    eprintln!(
+        "Client {} fell behind; disconnecting it: {}",
+        client.conn_id, why
+    );
+    client.outbox.closing.store(true, Ordering::Release);
+    client.outbox.kicked.notify_one();
+}
+
+/// Builds the `FullState` that replaces a stale client's skipped messages and
+/// starts queuing for it again, showing the board as `role` sees it. Returns
+/// `None` if the session is gone.
+///
+/// The flag clears on the session's actor, so every broadcast is either
+/// covered by this `FullState` or queued after it.
+pub async fn resync_payload(
+    state: &AppState,
+    session_id: &str,
+    role: ClientRole,
+    outbox: Arc<Outbox>,
+) -> Option<String> {
+    actor::call(state, session_id, move |actor| {
+        outbox.stale.store(false, Ordering::Release);
+        let msg = WsServerMsg::FullState {
+            session: actor.session.view(role),
+        };
+        match history::with_seq(&msg, actor.log.last_seq()) {
+            Ok(payload) => Some(payload),
+            Err(err) => {
+                eprintln!("Failed to serialize FullState: {}", err);
+                None
+            }
+        }
+    })
+    .await
+    .flatten()
+}
+
+/// Fills in a connection's queue metrics.
+pub fn annotate_stats(client: &WsClient, stat: &mut ConnectionStat) {
+    stat.queue_depth = client.tx.max_capacity() - client.tx.capacity();
+    stat.max_queue_depth = client.outbox.max_depth.load(Ordering::Relaxed);
+    stat.queue_overflows = client.outbox.overflows.load(Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{Duration, timeout};
+
+    fn client_with(policy: SlowConsumerPolicy, disconnect_after: u64) -> (WsClient, Inbox) {
+        let config = OutboxConfig {
+            capacity: 2,
+            policy,
+            disconnect_after,
+        };
+        client(config, ClientRole::Player)
+    }
+
+    fn fill(client: &WsClient) {
+        assert!(deliver(client, "first".to_string()));
+        assert!(deliver(client, "second".to_string()));
+    }
+
+    fn is_closing(client: &WsClient) -> bool {
+        client.outbox.closing.load(Ordering::Acquire)
+    }
+
+    #[test]
+    fn a_stale_client_gets_nothing_queued_until_resynced() {
+        let (client, mut inbox) = client_with(SlowConsumerPolicy::Resync, 5);
+        fill(&client);
+        assert!(deliver(&client, "overflow".to_string()));
+        assert!(client.outbox.is_stale());
+
+        // Later broadcasts are covered by the FullState, so they neither
+        // queue nor count as further overflows.
+        while inbox.broadcasts.try_recv().is_ok() {}
+        for _ in 0..10 {
+            assert!(deliver(&client, "skipped".to_string()));
+        }
+        assert!(inbox.broadcasts.try_recv().is_err());
+        assert_eq!(client.outbox.overflows.load(Ordering::Relaxed), 1);
+        assert_eq!(client.outbox.max_depth.load(Ordering::Relaxed), 2);
+        assert!(!is_closing(&client));
+    }
+
+    #[test]
+    fn repeated_overflows_disconnect_at_the_limit() {
+        let (client, mut inbox) = client_with(SlowConsumerPolicy::Disconnect, 3);
+        fill(&client);
+        assert!(deliver(&client, "dropped".to_string()));
+        assert!(deliver(&client, "dropped".to_string()));
+        assert!(!client.outbox.is_stale() && !is_closing(&client));
+        assert!(!deliver(&client, "dropped".to_string()));
+        assert!(is_closing(&client));
+
+        // Nothing more is queued once the client is on its way out.
+        while inbox.broadcasts.try_recv().is_ok() {}
+        assert!(!deliver(&client, "late".to_string()));
+        assert!(inbox.broadcasts.try_recv().is_err());
+    }
+
+    #[test]
+    fn a_limit_of_zero_never_disconnects() {
+        let (client, _inbox) = client_with(SlowConsumerPolicy::Disconnect, 0);
+        fill(&client);
+        assert!((0..100).all(|_| deliver(&client, "dropped".to_string())));
+        assert!(!is_closing(&client));
+        assert_eq!(client.outbox.overflows.load(Ordering::Relaxed), 100);
+    }
+
+    #[tokio::test]
+    async fn a_full_reply_queue_kicks_the_client() {
+        let (client, mut inbox) = client_with(SlowConsumerPolicy::Resync, 0);
+        reply(&client, "Ack".to_string());
+        reply(&client, "Ack".to_string());
+        assert!(!is_closing(&client));
+        reply(&client, "Ack".to_string());
+        assert!(is_closing(&client));
+        timeout(Duration::from_secs(1), client.outbox.kicked())
+            .await
+            .expect("send task was woken");
+
+        assert!(!deliver(&client, "broadcast".to_string()));
+        while inbox.replies.try_recv().is_ok() {}
+        reply(&client, "late".to_string());
+        assert!(inbox.replies.try_recv().is_err());
+    }
+}
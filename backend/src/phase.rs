@@ -0,0 +1,97 @@
+//! Game phases for the Bible Challenge backend server.
+//! A session moves through a fixed set of phases (lobby, board, question open,
+//! answering, revealed, final, finished). Messages that only make sense in
+//! some phases check them here, and phase changes go through one transition
+//! table so illegal sequences are rejected instead of silently applied.
+
+use crate::models::{ErrorCode, GamePhase, Session, WsError};
+
+impl GamePhase {
+    /// Whether a session may move from this phase to `next`.
+    pub fn can_transition_to(self, next: GamePhase) -> bool {
+        use GamePhase::*;
+        match self {
+            Lobby => matches!(next, Board),
+            Board => matches!(next, Board | QuestionOpen | Final | Finished),
+            QuestionOpen => matches!(next, QuestionOpen | Answering | Revealed | Board),
+            Answering => matches!(next, Answering | QuestionOpen | Revealed | Board),
+            Revealed => matches!(next, Revealed | QuestionOpen | Board),
+            Final => matches!(next, Final | Board | Finished),
+            Finished => false,
+        }
+    }
+
+    /// Whether the phase shows a question (open, being answered, or revealed).
+    pub fn is_question(self) -> bool {
+        matches!(
+            self,
+            GamePhase::QuestionOpen | GamePhase::Answering | GamePhase::Revealed
+        )
+    }
+}
+
+/// Fails unless the session may move to `next`.
+pub fn check(session: &Session, next: GamePhase) -> Result<(), WsError> {
+    if session.phase.can_transition_to(next) {
+        Ok(())
+    } else {
+        Err(WsError::new(
+            ErrorCode::WrongPhase,
+            format!("Can't go from {:?} to {:?}", session.phase, next),
+        ))
+    }
+}
+
+/// Moves the session to `next` if the transition is allowed.
+pub fn transition(session: &mut Session, next: GamePhase) -> Result<(), WsError> {
+    check(session, next)?;
+    session.phase = next;
+    Ok(())
+}
+
+/// Fails unless the session is in one of `allowed`. `action` completes
+/// "Can't … during <phase>".
+pub fn require(session: &Session, allowed: &[GamePhase], action: &str) -> Result<(), WsError> {
+    if allowed.contains(&session.phase) {
+        Ok(())
+    } else {
+        Err(WsError::new(
+            ErrorCode::WrongPhase,
+            format!("Can't {} during {:?}", action, session.phase),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use GamePhase::*;
+
+    const ALL: [GamePhase; 7] = [Lobby, Board, QuestionOpen, Answering, Revealed, Final, Finished];
+
+    #[test]
+    fn transition_table() {
+        let allowed = |from: GamePhase| -> Vec<GamePhase> {
+            ALL.into_iter().filter(|to| from.can_transition_to(*to)).collect()
+        };
+        assert_eq!(allowed(Lobby), [Board]);
+        assert_eq!(allowed(Board), [Board, QuestionOpen, Final, Finished]);
+        assert_eq!(allowed(QuestionOpen), [Board, QuestionOpen, Answering, Revealed]);
+        assert_eq!(allowed(Answering), [Board, QuestionOpen, Answering, Revealed]);
+        assert_eq!(allowed(Revealed), [Board, QuestionOpen, Revealed]);
+        assert_eq!(allowed(Final), [Board, Final, Finished]);
+        assert!(allowed(Finished).is_empty());
+    }
+
+    #[test]
+    fn rejected_moves_leave_the_phase_alone() {
+        let mut session = Session::new();
+        let err = transition(&mut session, QuestionOpen).err().unwrap();
+        assert_eq!(err.code, ErrorCode::WrongPhase);
+        assert_eq!(session.phase, Lobby);
+        transition(&mut session, Board).unwrap();
+        assert_eq!(session.phase, Board);
+        assert!(require(&session, &[QuestionOpen, Answering], "judge").is_err());
+        assert!(require(&session, &[Board], "open a cell").is_ok());
+    }
+}
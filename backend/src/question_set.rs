@@ -0,0 +1,506 @@
+//! Question-set parsing for the Bible Challenge backend server.
+//! Parses the host's TSV uploads and the `sample_questions.json` shape into typed
+//! questions, reports row-level errors, and lays questions out as a `Board`.
+
+use crate::models::{Board, BoardCategory, BoardCell};
+use serde::{Deserialize, Serialize};
+
+/// A single parsed question, before it is placed on a board.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QuestionEntry {
+    pub question: String,
+    pub answer: String,
+    pub reference: String,
+    pub category: Option<String>,
+    pub points: Option<i32>,
+    #[serde(default)]
+    pub daily_double: bool,
+    /// The name of the round the question belongs to, for multi-round games.
+    #[serde(default)]
+    pub round: Option<String>,
+}
+
+/// A validated list of questions from an uploaded file.
+#[derive(Serialize, Clone)]
+pub struct QuestionSet {
+    pub questions: Vec<QuestionEntry>,
+}
+
+/// A problem with one row of an uploaded file.
+///
+/// `row` is the 1-based line number for TSV (the header is line 1) and the
+/// 1-based array position for JSON. Row 0 refers to the file as a whole.
+/// `column` names the offending TSV header or JSON field, when there is one.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RowError {
+    pub row: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl RowError {
+    pub(crate) fn new(row: usize, message: impl Into<String>) -> Self {
+        Self {
+            row,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    fn in_column(row: usize, column: &str, message: impl Into<String>) -> Self {
+        Self {
+            column: Some(column.to_string()),
+            ..Self::new(row, message)
+        }
+    }
+}
+
+/// Columns recognised in a TSV header, matched case-insensitively.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Question,
+    Answer,
+    Reference,
+    Category,
+    Points,
+    DailyDouble,
+    Round,
+}
+
+impl Column {
+    fn from_header(header: &str) -> Option<Self> {
+        match header.trim().to_lowercase().as_str() {
+            "question" | "questions" => Some(Self::Question),
+            "answer" | "answers" => Some(Self::Answer),
+            "reference" | "references" => Some(Self::Reference),
+            "category" => Some(Self::Category),
+            "point value" | "point values" | "points" => Some(Self::Points),
+            "daily double" | "dailydouble" => Some(Self::DailyDouble),
+            "round" => Some(Self::Round),
+            _ => None,
+        }
+    }
+}
+
+/// The `sample_questions.json` shape used by the frontend.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonQuestion {
+    question_text: String,
+    answer_text: String,
+    #[serde(default)]
+    reference_text: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    point_value: Option<i32>,
+    #[serde(default)]
+    daily_double: bool,
+    #[serde(default)]
+    round: Option<String>,
+}
+
+/// Parses an uploaded question set, picking JSON or TSV from its first character.
+pub fn parse_question_set(text: &str) -> Result<QuestionSet, Vec<RowError>> {
+    if text.trim_start().starts_with('[') {
+        parse_json(text)
+    } else {
+        parse_tsv(text)
+    }
+}
+
+/// Parses a tab-separated file with a header row.
+///
+/// `Question` and `Answer` columns are required; `Reference`, `Category`,
+/// `Point Value` (or `Points`), `Daily Double` and `Round` are optional and
+/// may appear in any order.
+pub fn parse_tsv(text: &str) -> Result<QuestionSet, Vec<RowError>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_end_matches('\r')))
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let Some((_, header_line)) = lines.next() else {
+        return Err(vec![RowError::new(0, "File is empty")]);
+    };
+    let headers: Vec<&str> = header_line.split('\t').map(str::trim).collect();
+    let columns: Vec<Option<Column>> = headers.iter().map(|header| Column::from_header(header)).collect();
+    let header_of = |wanted: Column| {
+        let index = columns.iter().position(|column| *column == Some(wanted));
+        index.map_or("", |index| headers[index])
+    };
+    let mut errors = Vec::new();
+    for required in [Column::Question, Column::Answer] {
+        if !columns.contains(&Some(required)) {
+            let name = if required == Column::Question { "Question" } else { "Answer" };
+            errors.push(RowError::new(1, format!("Header is missing a `{}` column", name)));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut questions = Vec::new();
+    for (row, line) in lines {
+        let mut entry = QuestionEntry {
+            question: String::new(),
+            answer: String::new(),
+            reference: String::new(),
+            category: None,
+            points: None,
+            daily_double: false,
+            round: None,
+        };
+        let mut row_ok = true;
+        for ((column, header), value) in columns.iter().zip(&headers).zip(line.split('\t')) {
+            let value = value.trim();
+            match column {
+                Some(Column::Question) => entry.question = value.to_string(),
+                Some(Column::Answer) => entry.answer = value.to_string(),
+                Some(Column::Reference) => entry.reference = value.to_string(),
+                Some(Column::Category) if !value.is_empty() => {
+                    entry.category = Some(value.to_string());
+                }
+                Some(Column::Round) if !value.is_empty() => {
+                    entry.round = Some(value.to_string());
+                }
+                Some(Column::Points) if !value.is_empty() => match parse_points(value) {
+                    Ok(points) => entry.points = Some(points),
+                    Err(message) => {
+                        errors.push(RowError::in_column(row, header, message));
+                        row_ok = false;
+                    }
+                },
+                Some(Column::DailyDouble) => match parse_flag(value) {
+                    Ok(flag) => entry.daily_double = flag,
+                    Err(message) => {
+                        errors.push(RowError::in_column(row, header, message));
+                        row_ok = false;
+                    }
+                },
+                _ => {}
+            }
+        }
+        let required = (header_of(Column::Question), header_of(Column::Answer));
+        if let Err(row_errors) = check_required(row, &entry, required) {
+            errors.extend(row_errors);
+            row_ok = false;
+        }
+        if row_ok {
+            questions.push(entry);
+        }
+    }
+
+    finish(questions, errors)
+}
+
+/// Parses a JSON array in the `sample_questions.json` shape.
+pub fn parse_json(text: &str) -> Result<QuestionSet, Vec<RowError>> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(text)
+        .map_err(|err| vec![RowError::new(0, format!("Invalid JSON: {}", err))])?;
+
+    let mut questions = Vec::new();
+    let mut errors = Vec::new();
+    for (index, value) in values.into_iter().enumerate() {
+        let row = index + 1;
+        let parsed = match serde_json::from_value::<JsonQuestion>(value) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                errors.push(RowError::new(row, err.to_string()));
+                continue;
+            }
+        };
+        if parsed.point_value.is_some_and(|points| points < 0) {
+            errors.push(RowError::in_column(row, "pointValue", "Point value must not be negative"));
+            continue;
+        }
+        let entry = QuestionEntry {
+            question: parsed.question_text.trim().to_string(),
+            answer: parsed.answer_text.trim().to_string(),
+            reference: parsed.reference_text.trim().to_string(),
+            category: parsed.category.filter(|category| !category.trim().is_empty()),
+            points: parsed.point_value,
+            daily_double: parsed.daily_double,
+            round: parsed.round.filter(|round| !round.trim().is_empty()),
+        };
+        match check_required(row, &entry, ("questionText", "answerText")) {
+            Ok(()) => questions.push(entry),
+            Err(row_errors) => errors.extend(row_errors),
+        }
+    }
+
+    finish(questions, errors)
+}
+
+fn parse_points(value: &str) -> Result<i32, String> {
+    match value.parse::<i32>() {
+        Ok(points) if points >= 0 => Ok(points),
+        Ok(_) => Err("Point value must not be negative".to_string()),
+        Err(_) => Err(format!("Point value `{}` is not a whole number", value)),
+    }
+}
+
+/// Parses a yes/no column; blank means no.
+fn parse_flag(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "" | "no" | "n" | "false" | "0" => Ok(false),
+        "yes" | "y" | "true" | "1" | "x" => Ok(true),
+        _ => Err(format!("Daily double `{}` should be yes or no", value)),
+    }
+}
+
+/// Checks the question and answer are filled in; `columns` names where each
+/// came from, for the error.
+fn check_required(
+    row: usize,
+    entry: &QuestionEntry,
+    (question_column, answer_column): (&str, &str),
+) -> Result<(), Vec<RowError>> {
+    let mut errors = Vec::new();
+    if entry.question.is_empty() {
+        errors.push(RowError::in_column(row, question_column, "Question is empty"));
+    }
+    if entry.answer.is_empty() {
+        errors.push(RowError::in_column(row, answer_column, "Answer is empty"));
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors) }
+}
+
+fn finish(questions: Vec<QuestionEntry>, errors: Vec<RowError>) -> Result<QuestionSet, Vec<RowError>> {
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    if questions.is_empty() {
+        return Err(vec![RowError::new(0, "File contains no questions")]);
+    }
+    Ok(QuestionSet { questions })
+}
+
+impl QuestionSet {
+    /// Lays the questions out the same way the host board does.
+    ///
+    /// With categories, each category becomes a column (in order of first
+    /// appearance) sorted by point value. Without categories, questions fill
+    /// columns of 5, 4 or 3 rows, whichever divides evenly (falling back to 3).
+    /// Missing or zero point values default to 100 × row. Every value is then
+    /// scaled by the round's `multiplier`; a cell whose scaled value doesn't
+    /// fit is reported by category and row.
+    pub fn into_board(self, multiplier: i32) -> Result<Board, Vec<RowError>> {
+        let has_categories = self.questions.iter().any(|q| q.category.is_some());
+        let columns: Vec<(String, Vec<QuestionEntry>)> = if has_categories {
+            let mut columns: Vec<(String, Vec<QuestionEntry>)> = Vec::new();
+            for entry in self.questions {
+                let name = entry.category.clone().unwrap_or_default();
+                match columns.iter_mut().find(|(existing, _)| *existing == name) {
+                    Some((_, entries)) => entries.push(entry),
+                    None => columns.push((name, vec![entry])),
+                }
+            }
+            for (_, entries) in &mut columns {
+                entries.sort_by_key(|entry| entry.points.unwrap_or(0));
+            }
+            columns
+        } else {
+            let total = self.questions.len();
+            let rows = [5, 4, 3]
+                .into_iter()
+                .find(|rows| total.is_multiple_of(*rows))
+                .unwrap_or(3);
+            self.questions
+                .chunks(rows)
+                .enumerate()
+                .map(|(index, chunk)| (format!("Category {}", index + 1), chunk.to_vec()))
+                .collect()
+        };
+
+        let mut errors = Vec::new();
+        let mut categories = Vec::new();
+        for (name, entries) in columns {
+            let mut cells = Vec::new();
+            for (row, entry) in entries.into_iter().enumerate() {
+                // The host board treats a point value of 0 as "not set".
+                let base = entry
+                    .points
+                    .filter(|points| *points > 0)
+                    .unwrap_or((row as i32 + 1) * 100);
+                let Some(points) = base.checked_mul(multiplier) else {
+                    errors.push(RowError::new(
+                        0,
+                        format!(
+                            "`{}`, row {}: {} points × {} is too large",
+                            name,
+                            row + 1,
+                            base,
+                            multiplier
+                        ),
+                    ));
+                    continue;
+                };
+                cells.push(BoardCell {
+                    question: entry.question,
+                    answer: entry.answer,
+                    reference: entry.reference,
+                    points,
+                    state: Default::default(),
+                    answered_by: None,
+                    daily_double: entry.daily_double,
+                });
+            }
+            categories.push(BoardCategory { name, cells });
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Board {
+            categories,
+            active_cell: None,
+            daily_double: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FULL: &str = include_str!("../../frontend/examples/3_categories_3x3.tsv");
+    const NO_CATEGORY: &str = include_str!("../../frontend/examples/no_category.tsv");
+    const NO_POINT_VALUES: &str = include_str!("../../frontend/examples/no_point_values.tsv");
+    const SAMPLE_JSON: &str = include_str!("../../frontend/src/data/sample_questions.json");
+
+    fn column_names(board: &Board) -> Vec<&str> {
+        board.categories.iter().map(|category| category.name.as_str()).collect()
+    }
+
+    fn points(board: &Board, category: usize) -> Vec<i32> {
+        board.categories[category].cells.iter().map(|cell| cell.points).collect()
+    }
+
+    #[test]
+    fn parses_full_tsv() {
+        let set = parse_question_set(FULL).unwrap();
+        assert_eq!(set.questions.len(), 9);
+        let first = &set.questions[0];
+        assert_eq!(first.question, "Who built the ark?");
+        assert_eq!(first.answer, "Noah");
+        assert_eq!(first.reference, "Genesis 6-9");
+        assert_eq!(first.category.as_deref(), Some("Genesis"));
+        assert_eq!(first.points, Some(100));
+
+        let board = set.into_board(1).unwrap();
+        assert_eq!(column_names(&board), ["Genesis", "Exodus", "Joshua"]);
+        assert_eq!(points(&board, 1), [100, 200, 300]);
+    }
+
+    #[test]
+    fn lays_out_tsv_without_categories_in_columns_of_five() {
+        let set = parse_question_set(NO_CATEGORY).unwrap();
+        assert_eq!(set.questions.len(), 30);
+        assert!(set.questions.iter().all(|entry| entry.category.is_none()));
+
+        let board = set.into_board(1).unwrap();
+        assert_eq!(board.categories.len(), 6);
+        assert_eq!(board.categories[0].name, "Category 1");
+        assert!(board.categories.iter().all(|category| category.cells.len() == 5));
+    }
+
+    #[test]
+    fn defaults_missing_point_values_by_row() {
+        let set = parse_question_set(NO_POINT_VALUES).unwrap();
+        assert!(set.questions.iter().all(|entry| entry.points.is_none()));
+
+        let board = set.into_board(2).unwrap();
+        assert_eq!(column_names(&board), ["Kings", "Prophets", "Books", "Genesis", "Revelation"]);
+        assert_eq!(points(&board, 0), [200, 400, 600]);
+        assert_eq!(points(&board, 3), [200]);
+    }
+
+    #[test]
+    fn rejects_point_values_that_overflow_the_multiplier() {
+        let text = "Category\tQuestion\tAnswer\tPoints\n\
+                    Kings\tWho was first?\tSaul\t100\n\
+                    Kings\tWho was next?\tDavid\t1000000000\n";
+        let set = parse_question_set(text).unwrap();
+        assert_eq!(points(&set.clone().into_board(2).unwrap(), 0), [200, 2_000_000_000]);
+
+        let errors = set.into_board(3).err().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "`Kings`, row 2: 1000000000 points × 3 is too large");
+    }
+
+    #[test]
+    fn parses_sample_json() {
+        let set = parse_question_set(SAMPLE_JSON).unwrap();
+        assert_eq!(set.questions.len(), 30);
+        let first = &set.questions[0];
+        assert_eq!(first.question, "By which gate in Jerusalem is Bethesda?");
+        assert_eq!(first.answer, "Sheep Gate");
+        assert_eq!(first.reference, "John 5:2");
+        assert_eq!(first.points, Some(100));
+
+        let board = set.into_board(1).unwrap();
+        assert_eq!(board.categories.len(), 6);
+        assert_eq!(board.categories[0].name, "Category A");
+        assert_eq!(points(&board, 0), [100, 200, 300, 400, 500]);
+    }
+
+    #[test]
+    fn reports_row_and_column_of_bad_tsv_cells() {
+        let text = "Category\tQuestion\tAnswer\tPoints\tDaily Double\n\
+                    Kings\tWho was first?\tSaul\t100\tno\n\
+                    \n\
+                    Kings\tWho was next?\tDavid\tlots\tmaybe\n\
+                    Kings\t\tSolomon\t300\n\
+                    Kings\tWho lost the kingdom?\tRehoboam\t-5\n";
+        let errors = parse_question_set(text).err().unwrap();
+        let found: Vec<_> = errors
+            .iter()
+            .map(|err| (err.row, err.column.as_deref()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (4, Some("Points")),
+                (4, Some("Daily Double")),
+                (5, Some("Question")),
+                (6, Some("Points")),
+            ]
+        );
+        assert_eq!(errors[0].message, "Point value `lots` is not a whole number");
+        assert_eq!(errors[3].message, "Point value must not be negative");
+    }
+
+    #[test]
+    fn reports_missing_tsv_columns_on_the_header() {
+        let errors = parse_question_set("Question\tReference\nWho?\tNowhere\n").err().unwrap();
+        assert_eq!(errors, [RowError::new(1, "Header is missing a `Answer` column")]);
+    }
+
+    #[test]
+    fn reports_bad_json_entries_by_position() {
+        let text = r#"[
+            {"questionText": "Who built the ark?", "answerText": "Noah"},
+            {"questionText": "Who was first?"},
+            {"questionText": "Who was next?", "answerText": " ", "pointValue": 200},
+            {"questionText": "Who lost?", "answerText": "Rehoboam", "pointValue": -1}
+        ]"#;
+        let errors = parse_question_set(text).err().unwrap();
+        let found: Vec<_> = errors
+            .iter()
+            .map(|err| (err.row, err.column.as_deref()))
+            .collect();
+        assert_eq!(found, [(2, None), (3, Some("answerText")), (4, Some("pointValue"))]);
+        assert!(errors[0].message.contains("answerText"));
+    }
+
+    #[test]
+    fn rejects_files_without_questions() {
+        let errors = parse_question_set("").err().unwrap();
+        assert_eq!(errors, [RowError::new(0, "File is empty")]);
+        let errors = parse_question_set("Question\tAnswer\n").err().unwrap();
+        assert_eq!(errors, [RowError::new(0, "File contains no questions")]);
+    }
+}
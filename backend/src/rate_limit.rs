@@ -0,0 +1,342 @@
+//! WebSocket flood protection for the Bible Challenge backend server.
+//!
+//! Every text frame a client sends takes a token from two buckets: one for its
+//! connection and one shared by every connection to the session. A message
+//! that finds either bucket empty is dropped and answered with `RateLimited`.
+//! Drops by the connection's own bucket also count as strikes; strikes are
+//! forgiven at one per second, and a connection that runs out of tolerance is
+//! closed with a policy-violation close frame.
+//!
+//! The connection's bucket lives in its connection task and the session's in
+//! its actor, so checking a message never takes a server-wide lock.
+//!
+//! Limits come from the environment (see `RateLimitConfig::from_env`); a rate
+//! of 0 turns that limit off.
+
+use crate::actor;
+use crate::config::{env_number, env_number_where};
+use crate::models::{AppState, ConnectionStat, ErrorCode, WsClient, WsError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+/// Close code sent to a connection disconnected for flooding (policy violation).
+pub const CLOSE_POLICY: u16 = 1008;
+
+/// Refill rate and capacity of a token bucket.
+#[derive(Clone, Copy)]
+pub struct BucketConfig {
+    /// Tokens added per second; 0 means unlimited.
+    pub rate_per_sec: f64,
+    /// Most tokens the bucket holds, i.e. the largest burst allowed.
+    pub burst: f64,
+}
+
+/// Limits applied to WebSocket messages.
+#[derive(Clone, Copy)]
+pub struct RateLimitConfig {
+    pub connection: BucketConfig,
+    pub session: BucketConfig,
+    /// How many dropped messages a connection may pile up before it is
+    /// disconnected; 0 never disconnects.
+    pub disconnect_after: u32,
+}
+
+impl RateLimitConfig {
+    /// Reads `WS_CONN_RATE`/`WS_CONN_BURST` (default 20/s, burst 40),
+    /// `WS_SESSION_RATE`/`WS_SESSION_BURST` (default 100/s, burst 200) and
+    /// `WS_FLOOD_DISCONNECT` (default 100 drops).
+    pub fn from_env() -> Self {
+        Self {
+            connection: BucketConfig {
+                rate_per_sec: env_limit("WS_CONN_RATE", 20.0),
+                burst: env_limit("WS_CONN_BURST", 40.0),
+            },
+            session: BucketConfig {
+                rate_per_sec: env_limit("WS_SESSION_RATE", 100.0),
+                burst: env_limit("WS_SESSION_BURST", 200.0),
+            },
+            disconnect_after: env_number("WS_FLOOD_DISCONNECT", 100),
+        }
+    }
+}
+
+/// Rates and bursts must be non-negative.
+fn env_limit(name: &str, default: f64) -> f64 {
+    env_number_where(name, default, |value| *value >= 0.0)
+}
+
+/// A classic token bucket, starting full.
+struct TokenBucket {
+    config: BucketConfig,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: BucketConfig) -> Self {
+        Self {
+            config,
+            tokens: config.burst,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Takes a token if one is available.
+    fn try_take(&mut self, now: Instant) -> bool {
+        if self.config.rate_per_sec == 0.0 {
+            return true;
+        }
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.config.rate_per_sec).min(self.config.burst);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A connection's bucket and drop tolerance, owned by its connection task.
+pub struct ConnectionLimit {
+    bucket: TokenBucket,
+    /// Remaining tolerance for drops; refills at one per second.
+    strikes: TokenBucket,
+    disconnect_after: u32,
+    /// Shared with the connection's `WsClient` so stats can read it.
+    dropped: Arc<AtomicU64>,
+    /// The session's total, shared with its `SessionLimit`.
+    session_dropped: Arc<AtomicU64>,
+}
+
+/// A session's shared bucket, owned by its actor.
+pub struct SessionLimit {
+    bucket: TokenBucket,
+    /// Every message dropped in the session, by either bucket.
+    dropped: Arc<AtomicU64>,
+}
+
+/// What to do with an incoming message.
+pub enum Verdict {
+    Allowed,
+    /// Drop the message and tell the sender why.
+    Dropped(WsError),
+    /// The connection has flooded past its tolerance; close it.
+    Disconnect,
+}
+
+impl SessionLimit {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            bucket: TokenBucket::new(config.session),
+            dropped: Arc::default(),
+        }
+    }
+
+    /// Takes a token for one message, counting a drop if there is none.
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        let allowed = self.bucket.try_take(now);
+        if !allowed {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        allowed
+    }
+
+    /// Counter for connections to add their own drops to.
+    pub fn dropped_counter(&self) -> Arc<AtomicU64> {
+        self.dropped.clone()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl ConnectionLimit {
+    /// Starts limiting a new connection; `session_dropped` comes from its
+    /// session's `SessionLimit::dropped_counter`.
+    pub fn new(
+        config: &RateLimitConfig,
+        client: &WsClient,
+        session_dropped: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            bucket: TokenBucket::new(config.connection),
+            strikes: TokenBucket::new(BucketConfig {
+                rate_per_sec: 1.0,
+                burst: f64::from(config.disconnect_after),
+            }),
+            disconnect_after: config.disconnect_after,
+            dropped: client.dropped_messages.clone(),
+            session_dropped,
+        }
+    }
+
+    fn count_drop(&self) -> u64 {
+        self.session_dropped.fetch_add(1, Ordering::Relaxed);
+        self.dropped.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Charges one message to the connection's own bucket, returning the
+    /// verdict if it was dropped there.
+    fn charge(&mut self, now: Instant) -> Option<Verdict> {
+        if self.bucket.try_take(now) {
+            return None;
+        }
+        self.count_drop();
+        if self.disconnect_after > 0 && !self.strikes.try_take(now) {
+            return Some(Verdict::Disconnect);
+        }
+        Some(Verdict::Dropped(WsError::new(
+            ErrorCode::RateLimited,
+            "Too many messages; slow down",
+        )))
+    }
+
+    /// Charges one incoming message to the connection and then its session.
+    pub async fn check(
+        &mut self,
+        state: &AppState,
+        session_id: &str,
+        client: &WsClient,
+    ) -> Verdict {
+        let now = Instant::now();
+        if let Some(verdict) = self.charge(now) {
+            if let Verdict::Disconnect = verdict {
+                eprintln!(
+                    "Disconnecting {} from session {} for flooding ({} messages dropped)",
+                    client.conn_id,
+                    session_id,
+                    self.dropped.load(Ordering::Relaxed)
+                );
+            }
+            return verdict;
+        }
+        let session_allowed = actor::call(state, session_id, move |actor| {
+            actor.rate_limit.try_take(now)
+        })
+        .await
+        .unwrap_or(true);
+        if !session_allowed {
+            // The session already counted it.
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return Verdict::Dropped(WsError::new(
+                ErrorCode::RateLimited,
+                "This session is receiving too many messages; try again shortly",
+            ));
+        }
+        Verdict::Allowed
+    }
+}
+
+/// Fills in a connection's drop count.
+pub fn annotate_stats(client: &WsClient, stat: &mut ConnectionStat) {
+    stat.dropped_messages = client.dropped_messages.load(Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn bucket(rate_per_sec: f64, burst: f64) -> (TokenBucket, Instant) {
+        let bucket = TokenBucket::new(BucketConfig {
+            rate_per_sec,
+            burst,
+        });
+        let start = bucket.refilled_at;
+        (bucket, start)
+    }
+
+    fn after(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    fn takes(bucket: &mut TokenBucket, now: Instant, count: usize) -> usize {
+        (0..count).filter(|_| bucket.try_take(now)).count()
+    }
+
+    #[test]
+    fn buckets_start_full_and_refill_at_their_rate() {
+        let (mut bucket, start) = bucket(10.0, 5.0);
+        assert_eq!(takes(&mut bucket, start, 8), 5);
+        // 10 per second is one token every 100ms.
+        assert!(!bucket.try_take(after(start, 50)));
+        assert!(bucket.try_take(after(start, 100)));
+        assert!(!bucket.try_take(after(start, 100)));
+        assert_eq!(takes(&mut bucket, after(start, 400), 8), 3);
+    }
+
+    #[test]
+    fn refills_never_exceed_the_burst() {
+        let (mut bucket, start) = bucket(10.0, 5.0);
+        assert_eq!(takes(&mut bucket, start, 5), 5);
+        assert_eq!(takes(&mut bucket, after(start, 60_000), 20), 5);
+    }
+
+    #[test]
+    fn a_rate_of_zero_is_unlimited() {
+        let (mut bucket, start) = bucket(0.0, 0.0);
+        assert_eq!(takes(&mut bucket, start, 1000), 1000);
+    }
+
+    fn connection(
+        rate_per_sec: f64,
+        burst: f64,
+        disconnect_after: u32,
+    ) -> (ConnectionLimit, Instant) {
+        let (bucket, start) = bucket(rate_per_sec, burst);
+        let strikes = BucketConfig {
+            rate_per_sec: 1.0,
+            burst: f64::from(disconnect_after),
+        };
+        let mut limit = ConnectionLimit {
+            bucket,
+            strikes: TokenBucket::new(strikes),
+            disconnect_after,
+            dropped: Arc::default(),
+            session_dropped: Arc::default(),
+        };
+        limit.strikes.refilled_at = start;
+        (limit, start)
+    }
+
+    #[test]
+    fn flooding_past_the_strikes_disconnects() {
+        let (mut limit, start) = connection(1.0, 1.0, 3);
+        assert!(limit.charge(start).is_none());
+        for _ in 0..3 {
+            assert!(matches!(limit.charge(start), Some(Verdict::Dropped(_))));
+        }
+        assert!(matches!(limit.charge(start), Some(Verdict::Disconnect)));
+        assert_eq!(limit.dropped.load(Ordering::Relaxed), 4);
+        assert_eq!(limit.session_dropped.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn strikes_are_forgiven_over_time() {
+        let (mut limit, start) = connection(1.0, 1.0, 2);
+        limit.charge(start);
+        assert!(matches!(limit.charge(start), Some(Verdict::Dropped(_))));
+        assert!(matches!(limit.charge(start), Some(Verdict::Dropped(_))));
+        // A second later the message gets through and one strike is forgiven.
+        assert!(limit.charge(after(start, 1000)).is_none());
+        assert!(matches!(
+            limit.charge(after(start, 1000)),
+            Some(Verdict::Dropped(_))
+        ));
+        assert!(matches!(
+            limit.charge(after(start, 1000)),
+            Some(Verdict::Disconnect)
+        ));
+    }
+
+    #[test]
+    fn a_tolerance_of_zero_never_disconnects() {
+        let (mut limit, start) = connection(1.0, 1.0, 0);
+        limit.charge(start);
+        assert!((0..100).all(|_| matches!(limit.charge(start), Some(Verdict::Dropped(_)))));
+    }
+}
@@ -0,0 +1,253 @@
+//! Multi-round games for the Bible Challenge backend server.
+//! An uploaded question set becomes a sequence of rounds (e.g. Single, Double
+//! and Final), each with its own board and point multiplier. The rounds come
+//! from a `Round` column in the upload, or else from a rounds config that
+//! splits the categories across the board rounds. The host moves on with
+//! `AdvanceRound`.
+
+use crate::models::{
+    ClientRole, ErrorCode, FinalQuestion, GamePhase, GameRound, Session, WsError, WsServerMsg,
+};
+use crate::phase;
+use crate::question_set::{QuestionEntry, QuestionSet, RowError};
+use std::env;
+
+/// Used when neither the upload nor `GAME_ROUNDS` configures rounds: one plain board.
+const DEFAULT_ROUNDS: &str = "Single:1";
+
+/// A configured round: its name and point multiplier.
+pub struct RoundSpec {
+    pub name: String,
+    pub multiplier: i32,
+}
+
+impl RoundSpec {
+    /// A round named `Final` is played as the final round.
+    fn is_final(&self) -> bool {
+        is_final_name(&self.name)
+    }
+}
+
+fn is_final_name(name: &str) -> bool {
+    name.trim().eq_ignore_ascii_case("final")
+}
+
+/// Parses a rounds config such as `Single:1,Double:2,Final`.
+///
+/// A round without a multiplier gets its position among the board rounds, so
+/// `Single,Double` means ×1 then ×2.
+pub fn parse_specs(text: &str) -> Result<Vec<RoundSpec>, String> {
+    let mut specs: Vec<RoundSpec> = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (name, multiplier) = match part.split_once(':') {
+            Some((name, multiplier)) => {
+                let multiplier = multiplier
+                    .trim()
+                    .parse::<i32>()
+                    .ok()
+                    .filter(|multiplier| *multiplier > 0)
+                    .ok_or_else(|| format!("Round `{}` needs a positive whole multiplier", name))?;
+                (name.trim(), multiplier)
+            }
+            None => {
+                let position = specs.iter().filter(|spec| !spec.is_final()).count();
+                (part, position as i32 + 1)
+            }
+        };
+        if name.is_empty() {
+            return Err("Round names must not be empty".to_string());
+        }
+        specs.push(RoundSpec {
+            name: name.to_string(),
+            multiplier,
+        });
+    }
+    if specs.iter().all(RoundSpec::is_final) {
+        return Err("Configure at least one board round".to_string());
+    }
+    Ok(specs)
+}
+
+/// The rounds config from `GAME_ROUNDS`, falling back to a single board.
+pub fn specs_from_env() -> Vec<RoundSpec> {
+    let text = env::var("GAME_ROUNDS").unwrap_or_else(|_| DEFAULT_ROUNDS.to_string());
+    parse_specs(&text).unwrap_or_else(|err| {
+        eprintln!("Ignoring invalid GAME_ROUNDS `{}`: {}", text, err);
+        parse_specs(DEFAULT_ROUNDS).expect("default rounds config is valid")
+    })
+}
+
+/// Lays an uploaded question set out as rounds.
+///
+/// With a `Round` column, questions are grouped by round in order of first
+/// appearance, and `specs` only supply multipliers for rounds it names. A
+/// `Final` round must hold exactly one question. Without one, the categories
+/// (or the questions, if there are no categories) are split in order across
+/// the board rounds in `specs`; a configured `Final` round is skipped since the
+/// upload has nothing to put in it.
+pub fn build(set: QuestionSet, specs: &[RoundSpec]) -> Result<Vec<GameRound>, Vec<RowError>> {
+    let groups = if set.questions.iter().any(|entry| entry.round.is_some()) {
+        group_by_round(set.questions)
+    } else {
+        split_across(set.questions, specs)?
+    };
+
+    let mut rounds = Vec::new();
+    let mut board_rounds = 0;
+    for (name, questions) in groups {
+        if is_final_name(&name) {
+            let [entry] = <[QuestionEntry; 1]>::try_from(questions).map_err(|_| {
+                vec![RowError::new(0, "The Final round needs exactly one question")]
+            })?;
+            rounds.push(GameRound {
+                name,
+                multiplier: 1,
+                board: None,
+                final_question: Some(FinalQuestion {
+                    category: entry.category.unwrap_or_else(|| "Final".to_string()),
+                    question: entry.question,
+                    answer: entry.answer,
+                    reference: entry.reference,
+                }),
+            });
+            continue;
+        }
+        board_rounds += 1;
+        let multiplier = specs
+            .iter()
+            .find(|spec| spec.name.eq_ignore_ascii_case(&name))
+            .map_or(board_rounds, |spec| spec.multiplier);
+        rounds.push(GameRound {
+            name,
+            multiplier,
+            board: Some(QuestionSet { questions }.into_board(multiplier)?),
+            final_question: None,
+        });
+    }
+    Ok(rounds)
+}
+
+fn group_by_round(questions: Vec<QuestionEntry>) -> Vec<(String, Vec<QuestionEntry>)> {
+    let mut groups: Vec<(String, Vec<QuestionEntry>)> = Vec::new();
+    for entry in questions {
+        let name = entry.round.clone().unwrap_or_default();
+        match groups
+            .iter_mut()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(&name))
+        {
+            Some((_, entries)) => entries.push(entry),
+            None => groups.push((name, vec![entry])),
+        }
+    }
+    groups
+}
+
+fn split_across(
+    questions: Vec<QuestionEntry>,
+    specs: &[RoundSpec],
+) -> Result<Vec<(String, Vec<QuestionEntry>)>, Vec<RowError>> {
+    let board_specs: Vec<&RoundSpec> = specs.iter().filter(|spec| !spec.is_final()).collect();
+    // Whole categories stay together; without categories each question stands alone.
+    let mut units: Vec<Vec<QuestionEntry>> = Vec::new();
+    for entry in questions {
+        let category = entry.category.clone();
+        match units
+            .iter_mut()
+            .find(|unit| category.is_some() && unit[0].category == category)
+        {
+            Some(unit) => unit.push(entry),
+            None => units.push(vec![entry]),
+        }
+    }
+    if units.len() < board_specs.len() {
+        return Err(vec![RowError::new(
+            0,
+            format!(
+                "{} categories can't fill {} rounds",
+                units.len(),
+                board_specs.len()
+            ),
+        )]);
+    }
+
+    // Spread the units as evenly as possible; later rounds take any extras.
+    let (total, count) = (units.len(), board_specs.len());
+    let mut units = units.into_iter();
+    Ok(board_specs
+        .iter()
+        .enumerate()
+        .map(|(index, spec)| {
+            let take = (index + 1) * total / count - index * total / count;
+            let questions = units.by_ref().take(take).flatten().collect();
+            (spec.name.clone(), questions)
+        })
+        .collect())
+}
+
+/// Installs uploaded rounds and makes the first one live. Loading a game is
+/// allowed in any phase and starts it over.
+pub fn install(session: &mut Session, mut rounds: Vec<GameRound>) -> WsServerMsg {
+    session.reset_buzzers();
+    session.board = rounds.first_mut().and_then(|round| round.board.take());
+    session.rounds = rounds;
+    session.current_round = 0;
+    session.final_round = None;
+    session.phase = phase_for(session);
+    WsServerMsg::GameLoaded {
+        rounds: session.rounds.iter().map(|round| round.view(ClientRole::Host)).collect(),
+        board: session.board.as_ref().map(|board| board.view(ClientRole::Host)),
+    }
+}
+
+/// Parks the live board and moves on to the next round, or finishes the game
+/// after the last one.
+pub fn advance(session: &mut Session) -> Result<WsServerMsg, WsError> {
+    if session.rounds.is_empty() {
+        return Err(WsError::new(
+            ErrorCode::InvalidState,
+            "No multi-round game is loaded",
+        ));
+    }
+    let current = session.current_round;
+    let next = current + 1;
+    let next_phase = match session.rounds.get(next) {
+        None => GamePhase::Finished,
+        Some(round) if round.is_final() => GamePhase::Final,
+        Some(_) => GamePhase::Board,
+    };
+    phase::check(session, next_phase)?;
+    session.rounds[current].board = session.board.take();
+    session.reset_buzzers();
+    session.phase = next_phase;
+
+    if next == session.rounds.len() {
+        return Ok(WsServerMsg::GameFinished);
+    }
+    session.current_round = next;
+    session.board = session.rounds[next].board.take();
+    let round = &session.rounds[next];
+    Ok(WsServerMsg::RoundStarted {
+        round: next,
+        name: round.name.clone(),
+        multiplier: round.multiplier,
+        board: session.board.as_ref().map(|board| board.view(ClientRole::Host)),
+        phase: next_phase,
+    })
+}
+
+/// The current round's uploaded final question, if it is a final round.
+pub fn final_question(session: &Session) -> Option<FinalQuestion> {
+    session
+        .rounds
+        .get(session.current_round)?
+        .final_question
+        .clone()
+}
+
+/// The phase the current round opens in: the board, or the final round.
+fn phase_for(session: &Session) -> GamePhase {
+    match session.rounds.get(session.current_round) {
+        Some(round) if round.is_final() => GamePhase::Final,
+        _ => GamePhase::Board,
+    }
+}
@@ -1,35 +1,60 @@
 //! Route handlers for the Bible Challenge backend server.
 //! This module defines HTTP endpoints and the WebSocket handler.
 
-use crate::models::{AppState, Session, Team, WsClientMsg, WsServerMsg};
-use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{Path, State, WebSocketUpgrade};
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use crate::models::{
+    AckResult, AppState, ClientRole, CommandResponse, ErrorCode, Session, StartSessionResponse, Team,
+    WsClient, WsClientEnvelope, WsClientMsg, WsError, WsServerMsg,
+};
+use crate::actor;
+use crate::auth;
+use crate::command::{self, Caller};
+use crate::daily_double;
+use crate::history;
+use crate::outbox;
+use crate::question_set::{parse_question_set, RowError};
+use crate::rate_limit::{self, ConnectionLimit, Verdict};
+use crate::rounds;
+use crate::seats;
+use crate::shutdown;
+use crate::store::forget_session;
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use axum::extract::{Path, Query, State, WebSocketUpgrade};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use chrono::Utc;
 use futures_util::{SinkExt, StreamExt};
 use rand::{distr::Alphabetic, Rng};
+use serde::Deserialize;
 use std::sync::Arc;
-use tokio::sync::Mutex as AsyncMutex;
 use tokio::time::{timeout, Duration};
 
-/// Broadcasts a server message to all WebSocket clients in a session.
-/// Removes clients whose send channel has been closed.
-async fn broadcast(state: &AppState, session_id: &str, msg: &WsServerMsg) {
+/// Sends a server message to a single WebSocket client.
+pub(crate) fn send_to(client: &WsClient, msg: &WsServerMsg) {
     match serde_json::to_string(msg) {
-        Ok(payload) => {
-            let mut clients = state.ws_clients.write().await;
-            if let Some(senders) = clients.get_mut(session_id) {
-                senders.retain(|tx| tx.send(payload.clone()).is_ok());
-            }
-        }
+        Ok(payload) => outbox::reply(client, payload),
         Err(err) => {
-            eprintln!("Failed to serialize WS server msg for broadcast: {}", err);
+            eprintln!("Failed to serialize WS server msg: {}", err);
         }
     }
 }
 
+/// Reports a rejected message to the connection that sent it.
+pub(crate) fn send_error(client: &WsClient, err: WsError, request_id: Option<String>) {
+    send_to(client, &WsServerMsg::Error {
+        code: err.code,
+        message: err.message,
+        request_id,
+    });
+}
+
+/// Best-effort `request_id` from a message that failed to parse, so the
+/// client can still match the error to its request.
+fn salvage_request_id(text: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    Some(value.get("request_id")?.as_str()?.to_string())
+}
+
 // ──────────────────────────────────────────────
 // WebSocket handler
 // ──────────────────────────────────────────────
@@ -42,94 +67,240 @@ async fn broadcast(state: &AppState, session_id: &str, msg: &WsServerMsg) {
 /// Listens for `WsClientMsg` from the client, applies mutations,
 /// and broadcasts the resulting `WsServerMsg` to all clients in the session.
 /// Includes a 30-second heartbeat: sends a Ping if no message is received.
+///
+/// Clients presenting `?host_token=` connect as host; everyone else is a player.
+/// A wrong token is rejected with `401` rather than downgraded.
+///
+/// A reconnecting client passes `?since=<seq>` to receive only the broadcasts
+/// it missed; if those are no longer buffered it gets a `FullState` instead.
 pub async fn ws_handler(
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<String>,
+    Query(params): Query<WsParams>,
     ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_ws_connection(state, session_id, socket))
+) -> Response {
+    if state.shutdown.is_started() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json("Server is shutting down")).into_response();
+    }
+    let role = match params.host_token.as_deref() {
+        None => ClientRole::Player,
+        Some(token) => match auth::is_host(&state, &session_id, Some(token)).await {
+            Some(true) => ClientRole::Host,
+            Some(false) => {
+                return (StatusCode::UNAUTHORIZED, Json("Invalid host token")).into_response();
+            }
+            None => return (StatusCode::NOT_FOUND, Json("Session not found")).into_response(),
+        },
+    };
+    let since = params.since;
+    ws.on_upgrade(move |socket| handle_ws_connection(state, session_id, role, since, socket))
+}
+
+/// Query parameters accepted by the WebSocket upgrade.
+#[derive(Deserialize)]
+pub struct WsParams {
+    host_token: Option<String>,
+    since: Option<u64>,
 }
 
-async fn handle_ws_connection(state: Arc<AppState>, session_id: String, socket: WebSocket) {
+async fn handle_ws_connection(
+    state: Arc<AppState>,
+    session_id: String,
+    role: ClientRole,
+    since: Option<u64>,
+    socket: WebSocket,
+) {
     let (mut sender, mut receiver) = socket.split();
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let (client, mut inbox) = outbox::client(state.outbox, role);
+    let client_outbox = client.outbox.clone();
 
-    // Verify session exists and send full state
-    {
-        let sessions = state.sessions.read().await;
-        match sessions.get(&session_id) {
-            Some(session_mutex) => {
-                let session = session_mutex.lock().await;
+    // Catch the client up and register it on the session's actor, so no
+    // broadcast falls in between.
+    let registering = client.clone();
+    let registered = actor::call(&state, &session_id, move |actor| {
+        let missed = since.and_then(|since| actor.log.since(since, registering.role));
+        let catch_up = match missed {
+            Some(missed) => missed,
+            None => {
                 let msg = WsServerMsg::FullState {
-                    session: session.clone(),
+                    session: actor.session.view(registering.role),
                 };
-                match serde_json::to_string(&msg) {
-                    Ok(payload) => {
-                        if sender.send(Message::Text(payload)).await.is_err() {
-                            return;
-                        }
-                    }
+                match history::with_seq(&msg, actor.log.last_seq()) {
+                    Ok(payload) => vec![payload],
                     Err(err) => {
                         eprintln!("Failed to serialize FullState: {}", err);
-                        return;
+                        return None;
                     }
                 }
             }
-            None => return,
+        };
+        // A catch-up longer than the queue turns into a FullState resync.
+        for payload in catch_up {
+            if !outbox::deliver(&registering, payload) {
+                break;
+            }
         }
-    }
+        if registering.role == ClientRole::Host {
+            let seats = seats::live_seats(&actor.clients);
+            send_to(&registering, &WsServerMsg::SeatsUpdate { seats });
+        }
+        actor.keep_alive();
+        actor.clients.push(registering);
+        Some(actor.rate_limit.dropped_counter())
+    })
+    .await;
+    let session_dropped = match registered {
+        Some(Some(session_dropped)) => session_dropped,
+        Some(None) => return,
+        None => {
+            // The session closed between the upgrade check and now.
+            let err = WsError::unknown_session();
+            let msg = WsServerMsg::Error {
+                code: err.code,
+                message: err.message,
+                request_id: None,
+            };
+            if let Ok(payload) = serde_json::to_string(&msg) {
+                let _ = sender.send(Message::Text(payload)).await;
+            }
+            return;
+        }
+    };
+    let mut limit = ConnectionLimit::new(&state.rate_limit, &client, session_dropped);
 
-    // Register this client
-    {
-        let mut clients = state.ws_clients.write().await;
-        clients
-            .entry(session_id.clone())
-            .or_default()
-            .push(tx.clone());
-    }
+    // Lets the receive side close the socket (with a reason) through the send side.
+    let (close_tx, mut close_rx) = tokio::sync::oneshot::channel::<CloseFrame<'static>>();
 
     // Forward outgoing messages from the channel to the WebSocket and send periodic Ping frames.
+    // A slow consumer is resynced here once its socket moves again, or
+    // dropped as soon as it's kicked, even mid-send.
+    let send_state = state.clone();
+    let send_session_id = session_id.clone();
     let mut send_task = tokio::spawn(async move {
         let mut ping_interval = tokio::time::interval(Duration::from_secs(30));
+        let mut shutdown_rx = send_state.shutdown.subscribe();
+        // Check once up front in case shutdown began while we were connecting.
+        shutdown_rx.mark_changed();
         loop {
-            tokio::select! {
+            if client_outbox.is_stale() {
+                // Every broadcast still queued predates the FullState that replaces it.
+                while inbox.broadcasts.try_recv().is_ok() {}
+                let Some(payload) = outbox::resync_payload(
+                    &send_state,
+                    &send_session_id,
+                    role,
+                    client_outbox.clone(),
+                )
+                .await
+                else {
+                    break;
+                };
+                tokio::select! {
+                    sent = sender.send(Message::Text(payload)) => if sent.is_err() { break },
+                    _ = client_outbox.kicked() => break,
+                }
+            }
+            let message = tokio::select! {
                 biased;
-                msg = rx.recv() => {
-                    match msg {
-                        Some(text) => {
-                            if sender.send(Message::Text(text)).await.is_err() {
-                                break;
-                            }
-                        }
-                        None => break,
+                _ = client_outbox.kicked() => break,
+                frame = &mut close_rx => {
+                    if let Ok(frame) = frame {
+                        let _ = sender.send(Message::Close(Some(frame))).await;
                     }
+                    break;
                 }
-                _ = ping_interval.tick() => {
-                    if sender.send(Message::Ping(vec![])).await.is_err() {
-                        break;
+                // Broadcasts first, so a command's events go out before its Ack.
+                msg = inbox.broadcasts.recv() => match msg {
+                    Some(text) => Message::Text(text),
+                    None => break,
+                },
+                msg = inbox.replies.recv() => match msg {
+                    Some(text) => Message::Text(text),
+                    None => break,
+                },
+                // Listed after the queues so everything queued is sent first.
+                _ = shutdown_rx.changed() => {
+                    if !*shutdown_rx.borrow() {
+                        continue;
                     }
+                    let _ = sender
+                        .send(Message::Close(Some(CloseFrame {
+                            code: shutdown::CLOSE_SERVICE_RESTART,
+                            reason: "Server restarting".into(),
+                        })))
+                        .await;
+                    break;
                 }
+                _ = ping_interval.tick() => Message::Ping(vec![]),
+            };
+            tokio::select! {
+                sent = sender.send(message) => if sent.is_err() { break },
+                _ = client_outbox.kicked() => break,
             }
         }
     });
 
-    // Process incoming messages with heartbeat
+    // Process incoming messages with heartbeat. Resolves to true if the
+    // connection is being closed for flooding.
     let recv_state = state.clone();
     let recv_session_id = session_id.clone();
+    let recv_client = client.clone();
     let mut recv_task = tokio::spawn(async move {
         loop {
             match timeout(Duration::from_secs(30), receiver.next()).await {
                 Ok(Some(Ok(Message::Text(text)))) => {
-                    if let Ok(msg) = serde_json::from_str::<WsClientMsg>(&text) {
-                        handle_ws_message(&recv_state, &recv_session_id, msg).await;
+                    let verdict = limit.check(&recv_state, &recv_session_id, &recv_client).await;
+                    let (request_id, result) = match verdict {
+                        Verdict::Disconnect => {
+                            let _ = close_tx.send(CloseFrame {
+                                code: rate_limit::CLOSE_POLICY,
+                                reason: "Too many messages".into(),
+                            });
+                            break true;
+                        }
+                        // Dropped messages aren't parsed beyond their request ID.
+                        Verdict::Dropped(err) => (salvage_request_id(&text), Err(err)),
+                        Verdict::Allowed => match serde_json::from_str::<WsClientEnvelope>(&text) {
+                            Ok(WsClientEnvelope { request_id, msg }) => {
+                                let caller = Caller::connection(&recv_client);
+                                let result =
+                                    command::execute(&recv_state, &recv_session_id, caller, msg)
+                                        .await
+                                        .map(|executed| {
+                                            if let Some(reply) = &executed.reply {
+                                                send_to(&recv_client, reply);
+                                            }
+                                            executed.result
+                                        });
+                                (request_id, result)
+                            }
+                            Err(err) => (
+                                salvage_request_id(&text),
+                                Err(WsError::new(ErrorCode::ParseError, err.to_string())),
+                            ),
+                        },
+                    };
+                    let ack_result = match result {
+                        Ok(result) => result,
+                        Err(err) => {
+                            let code = err.code;
+                            send_error(&recv_client, err, request_id.clone());
+                            AckResult::Rejected { code }
+                        }
+                    };
+                    if let Some(request_id) = request_id {
+                        send_to(&recv_client, &WsServerMsg::Ack {
+                            request_id,
+                            result: ack_result,
+                        });
                     }
                 }
-                Ok(Some(Ok(Message::Close(_)))) | Ok(None) => break,
+                Ok(Some(Ok(Message::Close(_)))) | Ok(None) => break false,
                 Ok(Some(Ok(Message::Ping(data)))) => {
                     // Axum auto-responds to pings; ignore payload
                     let _ = data;
                 }
-                Ok(Some(Err(_))) => break,
+                Ok(Some(Err(_))) => break false,
                 Err(_) => {
                     // Timeout waiting for client activity — no-op here.
                     // Periodic Ping frames are sent from the send task.
@@ -143,259 +314,41 @@ async fn handle_ws_connection(state: Arc<AppState>, session_id: String, socket:
     // Wait for either task to finish
     tokio::select! {
         _ = &mut send_task => recv_task.abort(),
-        _ = &mut recv_task => send_task.abort(),
-    }
-
-    // Remove client from registry
-    {
-        let mut clients = state.ws_clients.write().await;
-        if let Some(senders) = clients.get_mut(&session_id) {
-            senders.retain(|s| !s.same_channel(&tx));
-            if senders.is_empty() {
-                clients.remove(&session_id);
+        closing = &mut recv_task => {
+            // Give the send side a moment to deliver the close frame.
+            if matches!(closing, Ok(true)) {
+                let _ = timeout(Duration::from_secs(1), &mut send_task).await;
             }
+            send_task.abort();
         }
     }
-}
-
-async fn handle_ws_message(state: &AppState, session_id: &str, msg: WsClientMsg) {
-    let sessions = state.sessions.read().await;
-    let Some(session_mutex) = sessions.get(session_id) else {
-        return;
-    };
-    let mut session = session_mutex.lock().await;
 
-    match msg {
-        WsClientMsg::BuzzIn { team_index, client_timestamp } => {
-            if !session.buzz_lock {
-                let is_home = session.current_page == "home";
-                if let Some(team) = session.teams.get_mut(team_index) {
-                    if !team.has_buzzed {
-                        let server_time = Utc::now();
-                        let team_name = team.team_name.clone();
-                        
-                        // Parse client timestamp, ignore if malformed (show no feedback)
-                        let _ = chrono::DateTime::parse_from_rfc3339(&client_timestamp);
-                        
-                        team.buzz_lock_owned = true;
-                        team.last_buzz_attempt = Some(server_time);
-                        if !is_home {
-                            team.has_buzzed = true;
-                        }
-                        session.buzz_lock = true;
-                        session.last_modified = Utc::now();
-                        drop(session);
-                        drop(sessions);
-                        broadcast(state, session_id, &WsServerMsg::BuzzLocked { 
-                            team_index,
-                            server_timestamp: server_time,
-                            client_timestamp,
-                            team_name,
-                        }).await;
-                    }
-                }
-            }
+    // Remove client from the session
+    let conn_id = client.conn_id;
+    actor::call(&state, &session_id, move |actor| {
+        let was_seated = actor
+            .client(conn_id)
+            .is_some_and(|client| client.team_index.is_some());
+        actor.clients.retain(|client| client.conn_id != conn_id);
+        actor.keep_alive();
+        if was_seated {
+            seats::notify_hosts(actor);
         }
-        WsClientMsg::ReleaseBuzz => {
-            session.buzz_lock = false;
-            for team in &mut session.teams {
-                team.buzz_lock_owned = false;
-            }
-            session.last_modified = Utc::now();
-            drop(session);
-            drop(sessions);
-            broadcast(state, session_id, &WsServerMsg::BuzzReleased).await;
-        }
-        WsClientMsg::UpdateScore { team_index, score } => {
-            if let Some(team) = session.teams.get_mut(team_index) {
-                team.score = score;
-                session.last_modified = Utc::now();
-                drop(session);
-                drop(sessions);
-                broadcast(
-                    state,
-                    session_id,
-                    &WsServerMsg::ScoreUpdate { team_index, score },
-                )
-                .await;
-            }
-        }
-        WsClientMsg::UpdateTeamName { team_index, name } => {
-            if let Some(team) = session.teams.get_mut(team_index) {
-                team.team_name = name.clone();
-                session.last_modified = Utc::now();
-                drop(session);
-                drop(sessions);
-                broadcast(
-                    state,
-                    session_id,
-                    &WsServerMsg::TeamNameUpdate {
-                        team_index,
-                        name,
-                    },
-                )
-                .await;
-             }
-         }
-         WsClientMsg::LockBuzzers => {
-            session.buzz_lock = true;
-            for team in &mut session.teams {
-                team.buzz_lock_owned = false;
-                // Reset has_buzzed when timer expires
-                team.has_buzzed = false;
-            }
-            session.last_modified = Utc::now();
-            drop(session);
-            drop(sessions);
-            broadcast(state, session_id, &WsServerMsg::BuzzersLocked).await;
-        }
-         WsClientMsg::UpdateDarkMode { enabled } => {
-             session.dark_mode = enabled;
-session.last_modified = Utc::now();
-              drop(session);
-              drop(sessions);
-              broadcast(state, session_id, &WsServerMsg::DarkModeUpdate { enabled }).await;
-          }
-          WsClientMsg::UpdateTimerEnabled { enabled } => {
-              session.timer_enabled = enabled;
-              session.last_modified = Utc::now();
-              drop(session);
-              drop(sessions);
-              broadcast(state, session_id, &WsServerMsg::TimerEnabledUpdate { enabled }).await;
-          }
-          WsClientMsg::AddTeam => {
-              let new_team_index = session.teams.len();
-let new_team = Team {
-                   team_name: format!("Team {}", new_team_index + 1),
-                   score: 0,
-                   buzz_lock_owned: false,
-                   has_buzzed: false,
-                   last_buzz_attempt: None,
-               };
-              session.teams.push(new_team.clone());
-              session.last_modified = Utc::now();
-              drop(session);
-              drop(sessions);
-              broadcast(state, session_id, &WsServerMsg::TeamAdded { team: new_team }).await;
-          }
-          WsClientMsg::RemoveTeam { team_index } => {
-              if team_index < session.teams.len() {
-                  session.teams.remove(team_index);
-                  session.last_modified = Utc::now();
-                  drop(session);
-                  drop(sessions);
-                  broadcast(state, session_id, &WsServerMsg::TeamRemoved { team_index }).await;
-              }
-          }
-          WsClientMsg::ResetHasBuzzed => {
-              session.buzz_lock = false;
-              for team in &mut session.teams {
-                  team.buzz_lock_owned = false;
-                  team.has_buzzed = false;
-              }
-              session.last_modified = Utc::now();
-              drop(session);
-              drop(sessions);
-              broadcast(state, session_id, &WsServerMsg::HasBuzzedReset).await;
-          }
-WsClientMsg::SetPage { page } => {
-               session.current_page = page;
-               session.last_modified = Utc::now();
-               if session.current_page == "home" {
-                   session.buzz_lock = false;
-                   for team in &mut session.teams {
-                       team.buzz_lock_owned = false;
-                       team.has_buzzed = false;
-                   }
-                   drop(session);
-                   drop(sessions);
-                   broadcast(state, session_id, &WsServerMsg::HasBuzzedReset).await;
-               } else {
-                   let page = session.current_page.clone();
-                   drop(session);
-                   drop(sessions);
-                   broadcast(state, session_id, &WsServerMsg::PageUpdate { page }).await;
-               }
-           }
-           WsClientMsg::Ping { client_timestamp } => {
-               let server_time = Utc::now();
-               drop(session);
-               drop(sessions);
-               broadcast(state, session_id, &WsServerMsg::Pong {
-                   server_timestamp: server_time,
-                   client_timestamp,
-               }).await;
-           }
-      }
-}
-
-// ──────────────────────────────────────────────
-// Shared state-update helpers (used by HTTP handlers)
-// ──────────────────────────────────────────────
-
-/// Applies a buzz lock for the given team, updates session state, and broadcasts via WS.
-async fn apply_buzz_lock(
-    state: &AppState,
-    session_id: &str,
-    team_index: usize,
-) -> Option<()> {
-    let sessions = state.sessions.read().await;
-    let session_mutex = sessions.get(session_id)?;
-    let mut session = session_mutex.lock().await;
-    if session.buzz_lock {
-        return None;
-    }
-    let is_home = session.current_page == "home";
-    let team = session.teams.get_mut(team_index)?;
-    if team.has_buzzed {
-        return None;
-    }
-    let server_time = Utc::now();
-    let team_name = team.team_name.clone();
-    team.buzz_lock_owned = true;
-    team.last_buzz_attempt = Some(server_time);
-    if !is_home {
-        team.has_buzzed = true;
-    }
-    session.buzz_lock = true;
-    session.last_modified = Utc::now();
-    drop(session);
-    drop(sessions);
-    // For HTTP, use current time as client timestamp placeholder
-    let client_timestamp = server_time.to_rfc3339();
-    broadcast(state, session_id, &WsServerMsg::BuzzLocked { 
-        team_index,
-        server_timestamp: server_time,
-        client_timestamp,
-        team_name,
-    }).await;
-    Some(())
-}
-
-/// Releases the buzz lock for the session and broadcasts via WS.
-async fn apply_buzz_release(state: &AppState, session_id: &str) -> bool {
-    let sessions = state.sessions.read().await;
-    let Some(session_mutex) = sessions.get(session_id) else {
-        return false;
-    };
-    let mut session = session_mutex.lock().await;
-    session.buzz_lock = false;
-    for team in &mut session.teams {
-        team.buzz_lock_owned = false;
-    }
-    session.last_modified = Utc::now();
-    drop(session);
-    drop(sessions);
-    broadcast(state, session_id, &WsServerMsg::BuzzReleased).await;
-    true
+    })
+    .await;
 }
 
 // ──────────────────────────────────────────────
 // HTTP route handlers
 // ──────────────────────────────────────────────
 
-/// `POST /session/start` — creates a new session and returns its ID.
-pub async fn start_session(State(state): State<Arc<AppState>>) -> Json<String> {
+/// `POST /session/start` — creates a new session and returns its ID and secret host token.
+///
+/// Responds `503` once the server is shutting down.
+pub async fn start_session(State(state): State<Arc<AppState>>) -> Response {
+    if state.shutdown.is_started() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json("Server is shutting down")).into_response();
+    }
     let session_id: String = rand::rng()
         .sample_iter(&Alphabetic)
         .take(4)
@@ -403,38 +356,9 @@ pub async fn start_session(State(state): State<Arc<AppState>>) -> Json<String> {
         .collect();
     let mut session_id = session_id.to_uppercase();
 
-    let now = Utc::now();
-    let session = Session {
-        created_at: now,
-        last_modified: now,
-        buzz_lock: false,
-        dark_mode: false,
-        timer_enabled: false,
-        current_page: "home".to_string(),
-        teams: vec![
-            Team {
-                team_name: "Team 1".to_string(),
-                score: 0,
-                buzz_lock_owned: false,
-                has_buzzed: false,
-                last_buzz_attempt: None,
-            },
-            Team {
-                team_name: "Team 2".to_string(),
-                score: 0,
-                buzz_lock_owned: false,
-                has_buzzed: false,
-                last_buzz_attempt: None,
-            },
-            Team {
-                team_name: "Team 3".to_string(),
-                score: 0,
-                buzz_lock_owned: false,
-                has_buzzed: false,
-                last_buzz_attempt: None,
-            },
-        ],
-    };
+    let mut session = Session::new();
+    session.host_token = auth::generate_host_token();
+    let host_token = session.host_token.clone();
     let mut sessions = state.sessions.write().await;
     while sessions.contains_key(&session_id) {
         session_id = rand::rng()
@@ -444,8 +368,16 @@ pub async fn start_session(State(state): State<Arc<AppState>>) -> Json<String> {
             .collect();
         session_id = session_id.to_uppercase();
     }
-    sessions.insert(session_id.clone(), AsyncMutex::new(session));
-    Json(session_id)
+    let handle = actor::spawn(&state, session_id.clone(), session);
+    sessions.insert(session_id.clone(), handle.clone());
+    drop(sessions);
+    // The actor writes the first snapshot, off the sessions lock.
+    handle.call(|actor| actor.mark_dirty()).await;
+    Json(StartSessionResponse {
+        session_id,
+        host_token,
+    })
+    .into_response()
 }
 
 /// `GET /session/:id` — checks if a session exists.
@@ -466,12 +398,9 @@ pub async fn get_session_team_info(
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<String>,
 ) -> impl IntoResponse {
-    let sessions = state.sessions.read().await;
-    if let Some(session_mutex) = sessions.get(&session_id) {
-        let session = session_mutex.lock().await;
-        (StatusCode::OK, Json(Some(session.teams.clone())))
-    } else {
-        (StatusCode::NOT_FOUND, Json(None))
+    match actor::call(&state, &session_id, |actor| actor.session.teams.clone()).await {
+        Some(teams) => (StatusCode::OK, Json(Some(teams))),
+        None => (StatusCode::NOT_FOUND, Json(None)),
     }
 }
 
@@ -481,37 +410,13 @@ pub async fn modify_session_team_info(
     Path((session_id, team_index)): Path<(String, usize)>,
     Json(updated_team): Json<Team>,
 ) -> impl IntoResponse {
-    let sessions = state.sessions.read().await;
-    if let Some(session_mutex) = sessions.get(&session_id) {
-        let mut session = session_mutex.lock().await;
-        if let Some(team) = session.teams.get_mut(team_index) {
-            let response_team = updated_team.clone();
-            *team = updated_team;
-            session.last_modified = Utc::now();
-            drop(session);
-            drop(sessions);
-            broadcast(
-                &state,
-                &session_id,
-                &WsServerMsg::FullState {
-                    session: {
-                        let sessions = state.sessions.read().await;
-                        sessions
-                            .get(&session_id)
-                            .unwrap()
-                            .lock()
-                            .await
-                            .clone()
-                    },
-                },
-            )
-            .await;
-            (StatusCode::OK, Json(Some(response_team)))
-        } else {
-            (StatusCode::NOT_FOUND, Json(None))
-        }
-    } else {
-        (StatusCode::NOT_FOUND, Json(None))
+    let msg = WsClientMsg::UpdateTeam {
+        team_index,
+        team: updated_team.clone(),
+    };
+    match command::execute(&state, &session_id, Caller::http_host(), msg).await {
+        Ok(_) => (StatusCode::OK, Json(Some(updated_team))),
+        Err(err) => (rejected_status(err.code), Json(None)),
     }
 }
 
@@ -520,15 +425,11 @@ pub async fn close_session(
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<String>,
 ) -> impl IntoResponse {
-    // Notify all connected WS clients before removal
-    broadcast(&state, &session_id, &WsServerMsg::SessionClosed).await;
-
-    // Remove WS clients
-    state.ws_clients.write().await.remove(&session_id);
-
-    // Remove session
-    let removed = state.sessions.write().await.remove(&session_id).is_some();
+    // The actor notifies all connected WS clients before it stops
+    // Only a live session's snapshot is deleted; anything else is a 404.
+    let removed = actor::close(&state, &session_id).await;
     if removed {
+        forget_session(&state, &session_id).await;
         (StatusCode::OK, Json(true))
     } else {
         (StatusCode::NOT_FOUND, Json(false))
@@ -540,33 +441,145 @@ pub async fn release_buzz_lock(
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<String>,
 ) -> impl IntoResponse {
-    if apply_buzz_release(&state, &session_id).await {
-        (StatusCode::OK, Json("Buzz lock released"))
-    } else {
-        (StatusCode::NOT_FOUND, Json("Session not found"))
+    match command::execute(&state, &session_id, Caller::http_host(), WsClientMsg::ReleaseBuzz).await {
+        Ok(_) => (StatusCode::OK, Json("Buzz lock released")),
+        Err(_) => (StatusCode::NOT_FOUND, Json("Session not found")),
     }
 }
 
-/// `POST /session/:id/buzz/:index` — acquires the buzz lock for a team via HTTP.
+/// `POST /session/:id/buzz/:index` — buzzes in for a team via HTTP.
+///
+/// Takes the host token, or the seat token of a player seated at that team,
+/// as `Authorization: Bearer <token>`; a seat at another team gets `403`.
+/// HTTP buzzes join the same arbitration window as WebSocket buzzes and are
+/// credited at their arrival time. `"Success"` means the buzz was accepted into
+/// the window (the winner is announced over WS); `"Queued"` means another team
+/// holds the lock and this team joined the buzz queue.
 pub async fn set_buzz_lock_owned(
     State(state): State<Arc<AppState>>,
     Path((session_id, team_index)): Path<(String, usize)>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    match apply_buzz_lock(&state, &session_id, team_index).await {
-        Some(_) => (StatusCode::OK, Json("Success")),
-        None => {
-            // Check if session exists vs buzz already locked
-            let sessions = state.sessions.read().await;
-            if let Some(session_mutex) = sessions.get(&session_id) {
-                let session = session_mutex.lock().await;
-                if session.buzz_lock {
-                    (StatusCode::OK, Json("Fail"))
-                } else {
-                    (StatusCode::NOT_FOUND, Json("Team not found"))
-                }
-            } else {
-                (StatusCode::NOT_FOUND, Json("Session not found"))
-            }
+    let presented = auth::bearer_token(&headers);
+    let caller = match (auth::is_host(&state, &session_id, presented).await, presented) {
+        (None, _) => return (StatusCode::NOT_FOUND, Json("Session or team not found")),
+        (Some(true), _) => Caller::http_host(),
+        (Some(false), Some(seat_token)) => Caller::http_seat(seat_token),
+        (Some(false), None) => return (StatusCode::UNAUTHORIZED, Json("Host or seat token required")),
+    };
+    let msg = WsClientMsg::BuzzIn {
+        team_index,
+        client_timestamp: Utc::now().to_rfc3339(),
+    };
+    match command::execute(&state, &session_id, caller, msg).await {
+        Ok(executed) if executed.result == AckResult::Queued => (StatusCode::OK, Json("Queued")),
+        Ok(_) => (StatusCode::OK, Json("Success")),
+        Err(err) if matches!(err.code, ErrorCode::UnknownSession | ErrorCode::InvalidIndex) => {
+            (StatusCode::NOT_FOUND, Json("Session or team not found"))
+        }
+        Err(err) if err.code == ErrorCode::Unauthorized => {
+            (StatusCode::FORBIDDEN, Json("Not seated at this team"))
+        }
+        Err(_) => (StatusCode::OK, Json("Fail")),
+    }
+}
+
+/// `POST /session/:id/commands` — runs any session command over HTTP.
+///
+/// The body is a command exactly as sent over the WebSocket, e.g.
+/// `{"type":"AddTeam"}` or `{"type":"OpenCell","category":0,"row":2}`. Events it
+/// causes are broadcast to the session's WebSocket clients as usual. The
+/// response reports the result the WebSocket `Ack` would carry, plus any reply
+/// meant for the sender alone (such as `ConnectionStats`). A rejected command
+/// gets a `4xx` status with `Error` as the reply.
+pub async fn run_command(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(msg): Json<WsClientMsg>,
+) -> impl IntoResponse {
+    match command::execute(&state, &session_id, Caller::http_host(), msg).await {
+        Ok(executed) => (
+            StatusCode::OK,
+            Json(CommandResponse {
+                result: executed.result,
+                reply: executed.reply,
+            }),
+        ),
+        Err(err) => (
+            rejected_status(err.code),
+            Json(CommandResponse {
+                result: AckResult::Rejected { code: err.code },
+                reply: Some(WsServerMsg::Error {
+                    code: err.code,
+                    message: err.message,
+                    request_id: None,
+                }),
+            }),
+        ),
+    }
+}
+
+/// The HTTP status a rejected command is answered with.
+fn rejected_status(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::ParseError | ErrorCode::InvalidIndex | ErrorCode::InvalidWager => {
+            StatusCode::BAD_REQUEST
         }
+        ErrorCode::UnknownSession => StatusCode::NOT_FOUND,
+        ErrorCode::Unauthorized => StatusCode::FORBIDDEN,
+        ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+        ErrorCode::LockHeld
+        | ErrorCode::InvalidState
+        | ErrorCode::WrongPhase
+        | ErrorCode::LockedOut => StatusCode::CONFLICT,
+    }
+}
+
+/// Query parameters accepted by the board upload.
+#[derive(Deserialize)]
+pub struct UploadBoardParams {
+    daily_doubles: Option<usize>,
+    rounds: Option<String>,
+}
+
+/// `POST /session/:id/board` — parses an uploaded TSV or JSON question set,
+/// installs it as the session's rounds with the first round's board live, and
+/// broadcasts it via WS.
+/// Rounds come from a `Round` column, or else `?rounds=Single:1,Double:2`
+/// (default `GAME_ROUNDS`) splits the categories across rounds.
+/// With `?daily_doubles=N`, each board that has no daily doubles flagged in
+/// the upload gets `N` picked at random. The response shows every round with
+/// its daily doubles; the broadcast keeps them hidden.
+///
+/// Responds `400` with the list of row errors if the upload is malformed.
+pub async fn upload_board(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Query(params): Query<UploadBoardParams>,
+    body: String,
+) -> impl IntoResponse {
+    let specs = match params.rounds.as_deref().map(rounds::parse_specs) {
+        Some(Ok(specs)) => specs,
+        Some(Err(message)) => {
+            return (StatusCode::BAD_REQUEST, Json(vec![RowError::new(0, message)])).into_response();
+        }
+        None => rounds::specs_from_env(),
+    };
+    let mut game = match parse_question_set(&body).and_then(|set| rounds::build(set, &specs)) {
+        Ok(game) => game,
+        Err(errors) => return (StatusCode::BAD_REQUEST, Json(errors)).into_response(),
+    };
+    if let Some(count) = params.daily_doubles {
+        for board in game.iter_mut().filter_map(|round| round.board.as_mut()) {
+            daily_double::assign_random(board, count);
+        }
+    }
+
+    let msg = WsClientMsg::LoadGame {
+        rounds: game.clone(),
+    };
+    match command::execute(&state, &session_id, Caller::http_host(), msg).await {
+        Ok(_) => (StatusCode::OK, Json(game)).into_response(),
+        Err(err) => (rejected_status(err.code), Json(err.message)).into_response(),
     }
 }
@@ -0,0 +1,236 @@
+//! Team seats for buzzer devices in the Bible Challenge backend server.
+//! A player connection claims a team seat and gets back a seat token; the
+//! connection is then bound to that team and may only buzz for it. Presenting
+//! the token again after a reconnect re-binds to the same seat. A token is
+//! bound to its team for good, and a seated connection can't claim another
+//! team until the host releases its seat. Nothing identifies a device beyond
+//! its token, though: one that drops its token and reconnects can claim
+//! another team. Hosts are told which teams currently have live buzzer
+//! devices, so they can spot that and release seats.
+
+use crate::actor::SessionActor;
+use crate::models::{ClientRole, ErrorCode, TeamSeat, WsClient, WsError, WsServerMsg};
+use crate::routes::send_to;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Generates a new seat token.
+fn generate_seat_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// Binds a connection to a team seat and returns `(team_index, seat_token)`.
+///
+/// A known `seat_token` restores the seat it was issued for and is never moved
+/// to another team; otherwise a new token is issued for `team_index`. A
+/// connection that already has a seat can only claim that one again. Fails if
+/// the team doesn't exist or the token is unknown and no team was given.
+/// Without a `conn_id` (a REST caller) the token is issued but no connection
+/// is bound.
+pub fn claim_seat(
+    actor: &mut SessionActor,
+    conn_id: Option<Uuid>,
+    team_index: Option<usize>,
+    seat_token: Option<String>,
+) -> Result<(usize, String), WsError> {
+    let seated = conn_id.and_then(|conn_id| seat_of(actor, conn_id));
+    let session = &mut actor.session;
+    let known = seat_token.and_then(|token| {
+        let seat = *session.seat_tokens.get(&token)?;
+        Some((token, seat))
+    });
+    let (team_index, seat_token) = match (known, team_index) {
+        (Some((_, seat)), Some(wanted)) if wanted != seat => return Err(seat_taken(seat)),
+        (Some((token, seat)), _) => (seat, Some(token)),
+        (None, Some(team_index)) => (team_index, None),
+        (None, None) => {
+            return Err(WsError::new(
+                ErrorCode::InvalidIndex,
+                "Unknown seat token; pick a team",
+            ));
+        }
+    };
+    if let Some(seated) = seated
+        && seated != team_index
+    {
+        return Err(seat_taken(seated));
+    }
+    if team_index >= session.teams.len() {
+        return Err(WsError::invalid_team(team_index));
+    }
+    let seat_token = match seat_token {
+        Some(token) => token,
+        None => {
+            let token = generate_seat_token();
+            session.seat_tokens.insert(token.clone(), team_index);
+            actor.touch();
+            token
+        }
+    };
+
+    if let Some(conn_id) = conn_id
+        && let Some(client) = actor.clients.iter_mut().find(|client| client.conn_id == conn_id)
+    {
+        client.team_index = Some(team_index);
+    }
+    notify_hosts(actor);
+    Ok((team_index, seat_token))
+}
+
+fn seat_taken(team_index: usize) -> WsError {
+    WsError::new(
+        ErrorCode::Unauthorized,
+        format!(
+            "This device already has the seat at team {}; ask the host to release it",
+            team_index
+        ),
+    )
+}
+
+/// Frees a team's seat: every seat token issued for it is revoked and its
+/// devices are unseated, so they can claim a team again. Returns the event to
+/// broadcast.
+pub fn release_seat(actor: &mut SessionActor, team_index: usize) -> Result<WsServerMsg, WsError> {
+    if team_index >= actor.session.teams.len() {
+        return Err(WsError::invalid_team(team_index));
+    }
+    actor
+        .session
+        .seat_tokens
+        .retain(|_, seat| *seat != team_index);
+    for client in actor.clients.iter_mut() {
+        if client.team_index == Some(team_index) {
+            client.team_index = None;
+        }
+    }
+    actor.touch();
+    notify_hosts(actor);
+    Ok(WsServerMsg::SeatReleased { team_index })
+}
+
+/// Returns the team a connection is seated at, if any.
+pub fn seat_of(actor: &SessionActor, conn_id: Uuid) -> Option<usize> {
+    actor.client(conn_id)?.team_index
+}
+
+/// Unseats connections at a removed team and shifts later seats down, matching
+/// the team list. Seat tokens in the session are adjusted by `Session::remove_seats`.
+pub fn team_removed(actor: &mut SessionActor, removed: usize) {
+    for client in actor.clients.iter_mut() {
+        client.team_index = match client.team_index {
+            Some(index) if index == removed => None,
+            Some(index) if index > removed => Some(index - 1),
+            other => other,
+        };
+    }
+    notify_hosts(actor);
+}
+
+/// Counts live buzzer devices per team.
+pub fn live_seats(clients: &[WsClient]) -> Vec<TeamSeat> {
+    let mut devices = BTreeMap::new();
+    for team_index in clients.iter().filter_map(|client| client.team_index) {
+        *devices.entry(team_index).or_insert(0) += 1;
+    }
+    devices
+        .into_iter()
+        .map(|(team_index, devices)| TeamSeat {
+            team_index,
+            devices,
+        })
+        .collect()
+}
+
+/// Sends the current seat map to every host connection in the session.
+pub fn notify_hosts(actor: &SessionActor) {
+    let msg = WsServerMsg::SeatsUpdate {
+        seats: live_seats(&actor.clients),
+    };
+    for client in actor.clients.iter().filter(|client| client.role == ClientRole::Host) {
+        send_to(client, &msg);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor;
+    use crate::models::{AppState, Session};
+    use crate::outbox::{self, Inbox};
+
+    const ID: &str = "SEAT";
+
+    /// A session with two player connections, whose inboxes must stay alive.
+    async fn session_with_players() -> (std::sync::Arc<AppState>, [Uuid; 2], Vec<Inbox>) {
+        let state = AppState::new(None);
+        let handle = actor::spawn(&state, ID.to_string(), Session::new());
+        state.sessions.write().await.insert(ID.to_string(), handle);
+        let (first, first_inbox) = outbox::client(state.outbox, ClientRole::Player);
+        let (second, second_inbox) = outbox::client(state.outbox, ClientRole::Player);
+        let conn_ids = [first.conn_id, second.conn_id];
+        actor::call(&state, ID, move |actor| actor.clients.extend([first, second])).await;
+        (state, conn_ids, vec![first_inbox, second_inbox])
+    }
+
+    async fn claim(
+        state: &AppState,
+        conn_id: Uuid,
+        team_index: Option<usize>,
+        seat_token: Option<&str>,
+    ) -> Result<(usize, String), WsError> {
+        let seat_token = seat_token.map(str::to_string);
+        actor::call(state, ID, move |actor| {
+            claim_seat(actor, Some(conn_id), team_index, seat_token)
+        })
+        .await
+        .unwrap()
+    }
+
+    async fn seat(state: &AppState, conn_id: Uuid) -> Option<usize> {
+        actor::call(state, ID, move |actor| seat_of(actor, conn_id)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn tokens_restore_their_own_seat_only() {
+        let (state, [first, second], _inboxes) = session_with_players().await;
+        let (team, token) = claim(&state, first, Some(1), None).await.unwrap();
+        assert_eq!(team, 1);
+        assert_eq!(seat(&state, first).await, Some(1));
+
+        // Another device presenting the token is seated at the same team.
+        assert_eq!(claim(&state, second, None, Some(&token)).await.unwrap(), (1, token.clone()));
+        assert_eq!(seat(&state, second).await, Some(1));
+
+        let err = claim(&state, second, Some(2), Some(&token)).await.err().unwrap();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+        let err = claim(&state, first, None, Some("forged")).await.err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidIndex);
+    }
+
+    #[tokio::test]
+    async fn a_seated_connection_cannot_steal_another_team() {
+        let (state, [first, _], _inboxes) = session_with_players().await;
+        claim(&state, first, Some(0), None).await.unwrap();
+        let err = claim(&state, first, Some(2), None).await.err().unwrap();
+        assert_eq!(err.code, ErrorCode::Unauthorized);
+        assert_eq!(seat(&state, first).await, Some(0));
+    }
+
+    #[tokio::test]
+    async fn releasing_a_seat_revokes_its_tokens() {
+        let (state, [first, second], _inboxes) = session_with_players().await;
+        let (_, token) = claim(&state, first, Some(0), None).await.unwrap();
+        claim(&state, second, Some(1), None).await.unwrap();
+
+        actor::call(&state, ID, |actor| release_seat(actor, 0)).await.unwrap().unwrap();
+        assert_eq!(seat(&state, first).await, None);
+        assert_eq!(seat(&state, second).await, Some(1));
+        let err = claim(&state, first, None, Some(&token)).await.err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidIndex);
+
+        // Unseated, the device may pick any team again.
+        assert_eq!(claim(&state, first, Some(2), None).await.unwrap().0, 2);
+        let err = actor::call(&state, ID, |actor| release_seat(actor, 3)).await.unwrap();
+        assert_eq!(err.err().unwrap().code, ErrorCode::InvalidIndex);
+    }
+}
@@ -0,0 +1,109 @@
+//! Graceful shutdown for the Bible Challenge backend server.
+//! On SIGINT or SIGTERM the server stops starting sessions and accepting
+//! WebSocket connections. Each session's actor then finishes the commands
+//! already queued for it, broadcasts `ServerShuttingDown` with a hint of when
+//! to reconnect, and writes a final snapshot if a store is configured. Sockets
+//! are closed with `1012` (service restart) once their queues drain, waiting
+//! at most `SHUTDOWN_GRACE_SECS` for slow clients.
+
+use crate::config::env_number;
+use crate::models::{AppState, WsServerMsg};
+use futures_util::future::join_all;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::watch;
+use tokio::time::{Duration, timeout};
+
+/// Close code sent to every connection when the server shuts down.
+pub const CLOSE_SERVICE_RESTART: u16 = 1012;
+
+/// Shutdown progress shared with every connection.
+pub struct Shutdown {
+    /// Sent to clients in `ServerShuttingDown`.
+    reconnect_after_ms: u64,
+    /// Longest to wait for sockets to close.
+    grace: Duration,
+    started: AtomicBool,
+    /// Flips to true once sockets should close; every send task holds a receiver.
+    close_sockets: watch::Sender<bool>,
+}
+
+impl Shutdown {
+    /// Reads `SHUTDOWN_RECONNECT_MS` (default 5000) and `SHUTDOWN_GRACE_SECS` (default 5).
+    pub fn from_env() -> Self {
+        let (close_sockets, _) = watch::channel(false);
+        Self {
+            reconnect_after_ms: env_number("SHUTDOWN_RECONNECT_MS", 5000),
+            grace: Duration::from_secs(env_number("SHUTDOWN_GRACE_SECS", 5)),
+            started: AtomicBool::new(false),
+            close_sockets,
+        }
+    }
+
+    /// Whether the server is shutting down and should turn away new work.
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::Acquire)
+    }
+
+    /// A receiver whose value turns true once connections should close.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.close_sockets.subscribe()
+    }
+}
+
+/// Waits for SIGINT or SIGTERM, then winds every session down. The server
+/// stops accepting connections once this resolves.
+pub async fn on_signal(state: Arc<AppState>) {
+    let interrupt = tokio::signal::ctrl_c();
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                eprintln!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+    println!("Shutting down");
+    drain(&state).await;
+}
+
+/// Stops every session actor after its queued commands, then closes sockets.
+async fn drain(state: &AppState) {
+    let shutdown = &state.shutdown;
+    shutdown.started.store(true, Ordering::Release);
+
+    let sessions = std::mem::take(&mut *state.sessions.write().await);
+    let count = sessions.len();
+    let notice = WsServerMsg::ServerShuttingDown {
+        reconnect_after_ms: shutdown.reconnect_after_ms,
+    };
+    join_all(sessions.into_values().map(|handle| {
+        let notice = notice.clone();
+        async move { handle.stop(notice, true).await }
+    }))
+    .await;
+    if state.store.is_some() {
+        println!("Saved {} session(s)", count);
+    }
+
+    // Send tasks flush what's queued, then close; each drops its receiver as it ends.
+    shutdown.close_sockets.send_replace(true);
+    if timeout(shutdown.grace, shutdown.close_sockets.closed())
+        .await
+        .is_err()
+    {
+        eprintln!("Gave up waiting for WebSocket connections to close");
+    }
+}
@@ -0,0 +1,208 @@
+//! Session persistence for the Bible Challenge backend server.
+//! Defines the pluggable `SessionStore` trait and the default file-backed store,
+//! which keeps one JSON snapshot per session so games survive a restart.
+
+use crate::actor;
+use crate::models::{AppState, Session};
+use chrono::Utc;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Storage backend for session snapshots.
+///
+/// Implementations are synchronous; callers run them on the blocking thread pool.
+pub trait SessionStore: Send + Sync {
+    /// Loads every stored session, keyed by session ID.
+    fn load_all(&self) -> io::Result<Vec<(String, Session)>>;
+    /// Writes (or overwrites) the snapshot for a session.
+    fn save(&self, session_id: &str, session: &Session) -> io::Result<()>;
+    /// Deletes the snapshot for a session. Missing snapshots are not an error.
+    fn remove(&self, session_id: &str) -> io::Result<()>;
+}
+
+/// Whether `session_id` has the shape `start_session` hands out: four
+/// uppercase ASCII letters. Anything else never names a snapshot file.
+pub fn is_session_id(session_id: &str) -> bool {
+    session_id.len() == 4 && session_id.bytes().all(|byte| byte.is_ascii_uppercase())
+}
+
+/// Stores each session as `<dir>/<SESSION_ID>.json`.
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// The snapshot path for a session, refusing IDs that could escape `dir`.
+    fn path_for(&self, session_id: &str, extension: &str) -> io::Result<PathBuf> {
+        if !is_session_id(session_id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid session ID {:?}", session_id),
+            ));
+        }
+        Ok(self.dir.join(format!("{}.{}", session_id, extension)))
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load_all(&self) -> io::Result<Vec<(String, Session)>> {
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(session_id) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .filter(|stem| is_session_id(stem))
+            else {
+                continue;
+            };
+            let contents = fs::read_to_string(&path)?;
+            match serde_json::from_str::<Session>(&contents) {
+                Ok(session) => sessions.push((session_id.to_string(), session)),
+                Err(err) => eprintln!("Skipping unreadable session file {:?}: {}", path, err),
+            }
+        }
+        Ok(sessions)
+    }
+
+    fn save(&self, session_id: &str, session: &Session) -> io::Result<()> {
+        let payload = serde_json::to_vec(session).map_err(io::Error::other)?;
+        // Write to a temp file then rename so a crash never leaves a half-written snapshot.
+        let path = self.path_for(session_id, "json")?;
+        let tmp_path = self.path_for(session_id, "json.tmp")?;
+        fs::write(&tmp_path, payload)?;
+        fs::rename(tmp_path, path)
+    }
+
+    fn remove(&self, session_id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path_for(session_id, "json")?) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Builds the session store selected by the environment.
+///
+/// `SESSION_STORE=none` disables persistence; otherwise sessions are written to
+/// `SESSION_STORE_DIR` (default `data/sessions`).
+pub fn store_from_env() -> Option<Arc<dyn SessionStore>> {
+    if env::var("SESSION_STORE").is_ok_and(|kind| kind.eq_ignore_ascii_case("none")) {
+        return None;
+    }
+    let dir = env::var("SESSION_STORE_DIR").unwrap_or_else(|_| "data/sessions".to_string());
+    match FileSessionStore::new(&dir) {
+        Ok(store) => Some(Arc::new(store)),
+        Err(err) => {
+            eprintln!("Failed to open session store at {}: {}", dir, err);
+            None
+        }
+    }
+}
+
+/// Reloads persisted sessions at startup, each with its own actor.
+///
+/// `last_modified` is refreshed so clients get a full idle window to reconnect
+/// before the cleanup task considers the session expired, and timers that were
+/// running when the server stopped pick up where they left off.
+pub async fn restore_sessions(state: &Arc<AppState>) {
+    let Some(store) = state.store.clone() else {
+        return;
+    };
+    let loaded = match tokio::task::spawn_blocking(move || store.load_all()).await {
+        Ok(Ok(loaded)) => loaded,
+        Ok(Err(err)) => {
+            eprintln!("Failed to load persisted sessions: {}", err);
+            return;
+        }
+        Err(err) => {
+            eprintln!("Session restore task failed: {}", err);
+            return;
+        }
+    };
+
+    let count = loaded.len();
+    let mut sessions = state.sessions.write().await;
+    let now = Utc::now();
+    for (session_id, mut session) in loaded {
+        session.last_modified = now;
+        let handle = actor::spawn(state, session_id.clone(), session);
+        sessions.insert(session_id, handle);
+    }
+    println!("Restored {} session(s) from store", count);
+}
+
+/// Writes a snapshot of the session to the configured store, if any.
+///
+/// The session's actor calls this between jobs so snapshots land in mutation order.
+pub async fn persist_session(state: &AppState, session_id: &str, session: &Session) {
+    let Some(store) = state.store.clone() else {
+        return;
+    };
+    let session_id = session_id.to_string();
+    let snapshot = session.clone();
+    let result = tokio::task::spawn_blocking(move || store.save(&session_id, &snapshot)).await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => eprintln!("Failed to persist session: {}", err),
+        Err(err) => eprintln!("Session persist task failed: {}", err),
+    }
+}
+
+/// Deletes a session's snapshot from the configured store, if any.
+pub async fn forget_session(state: &AppState, session_id: &str) {
+    let Some(store) = state.store.clone() else {
+        return;
+    };
+    let session_id = session_id.to_string();
+    let result = tokio::task::spawn_blocking(move || store.remove(&session_id)).await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => eprintln!("Failed to remove persisted session: {}", err),
+        Err(err) => eprintln!("Session remove task failed: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_ids_are_four_uppercase_letters() {
+        assert!(is_session_id("ABCD"));
+        for id in ["abcd", "ABC", "ABCDE", "AB1D", "../x", "..%2F", ""] {
+            assert!(!is_session_id(id), "{:?} accepted", id);
+        }
+    }
+
+    #[test]
+    fn store_refuses_paths_outside_its_dir() {
+        let root = env::temp_dir().join(format!("bc-store-{}", uuid::Uuid::new_v4()));
+        let store = FileSessionStore::new(root.join("sessions")).unwrap();
+        let victim = root.join("victim.json");
+        fs::write(&victim, "{}").unwrap();
+
+        let err = store.remove("../victim").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(store.save("../victim", &Session::new()).is_err());
+        assert!(victim.exists());
+
+        store.save("ABCD", &Session::new()).unwrap();
+        assert_eq!(store.load_all().unwrap().len(), 1);
+        store.remove("ABCD").unwrap();
+        assert!(store.load_all().unwrap().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+//! Server-side question and answer timers for the Bible Challenge backend server.
+//! Each session with a running timer gets a ticker task that counts down once per
+//! second, sends ticks, and applies buzzer lock/release on expiry when the
+//! session has `timer_enabled` set. The final round's timer always runs and
+//! closes final answers when it expires.
+//!
+//! Ticks aren't recorded for resync: a replayed tick would be stale, and the
+//! `FullState` a client resyncs with carries the remaining time.
+
+use crate::actor::{self, SessionActor};
+use crate::early_buzz;
+use crate::final_round;
+use crate::models::{AppState, GamePhase, JudgeOutcome, Session, TimerKind, TimerState, WsServerMsg};
+use std::sync::Arc;
+use tokio::time::{Duration, MissedTickBehavior};
+
+/// Starts (or restarts) a timer, using the session's configured duration unless one is given.
+pub fn start(session: &mut Session, kind: TimerKind, duration_secs: Option<u32>) -> WsServerMsg {
+    let duration_secs = duration_secs.unwrap_or(match kind {
+        TimerKind::Question => session.timers.question_secs,
+        TimerKind::Answer => session.timers.answer_secs,
+        TimerKind::Final => session.timers.final_secs,
+    });
+    *session.timers.slot_mut(kind) = Some(TimerState {
+        duration_secs,
+        remaining_secs: duration_secs,
+        running: duration_secs > 0,
+    });
+    WsServerMsg::TimerStarted {
+        kind,
+        duration_secs,
+    }
+}
+
+/// Pauses a running timer. Returns `None` if it isn't running.
+pub fn pause(session: &mut Session, kind: TimerKind) -> Option<WsServerMsg> {
+    let timer = session.timers.slot_mut(kind).as_mut()?;
+    if !timer.running {
+        return None;
+    }
+    timer.running = false;
+    Some(WsServerMsg::TimerPaused {
+        kind,
+        remaining_secs: timer.remaining_secs,
+    })
+}
+
+/// Resumes a paused timer. Returns `None` if it is running or already expired.
+pub fn resume(session: &mut Session, kind: TimerKind) -> Option<WsServerMsg> {
+    let timer = session.timers.slot_mut(kind).as_mut()?;
+    if timer.running || timer.remaining_secs == 0 {
+        return None;
+    }
+    timer.running = true;
+    Some(WsServerMsg::TimerResumed {
+        kind,
+        remaining_secs: timer.remaining_secs,
+    })
+}
+
+/// Removes a timer. Returns `None` if there was no timer to cancel.
+pub fn cancel(session: &mut Session, kind: TimerKind) -> Option<WsServerMsg> {
+    session
+        .timers
+        .slot_mut(kind)
+        .take()
+        .map(|_| WsServerMsg::TimerCancelled { kind })
+}
+
+/// Starts or cancels timers in response to a game event, mirroring what the
+/// host browser used to do locally. Only acts when `timer_enabled` is set,
+/// except that cancellations always apply.
+///
+/// Returns the timer events to broadcast after `event`.
+pub fn on_event(session: &mut Session, event: &WsServerMsg) -> Vec<WsServerMsg> {
+    let enabled = session.timer_enabled;
+    let mut events = Vec::new();
+    match event {
+        // With early-buzz penalties on, the question clock waits for `OpenBuzzers`.
+        WsServerMsg::PhaseChanged {
+            phase: GamePhase::QuestionOpen,
+        }
+        | WsServerMsg::CellOpened { .. }
+        | WsServerMsg::BuzzersOpened
+            if enabled && !early_buzz::awaiting_open(session) =>
+        {
+            events.extend(cancel(session, TimerKind::Answer));
+            events.push(start(session, TimerKind::Question, None));
+        }
+        WsServerMsg::FinalQuestionRevealed { duration_secs, .. } => {
+            events.push(start(session, TimerKind::Final, Some(*duration_secs)));
+        }
+        WsServerMsg::FinalAnswersClosed { .. } | WsServerMsg::FinalStarted { .. } => {
+            events.extend(cancel(session, TimerKind::Final));
+        }
+        WsServerMsg::WagerPlaced { .. } if enabled => {
+            events.push(start(session, TimerKind::Answer, None));
+        }
+        WsServerMsg::BuzzLocked { .. }
+        | WsServerMsg::AnswerJudged {
+            outcome: JudgeOutcome::PassedTo { .. },
+            ..
+        } if enabled && session.phase != GamePhase::Board && session.timers.question.is_some() => {
+            events.push(start(session, TimerKind::Answer, None));
+        }
+        WsServerMsg::BuzzReleased
+        | WsServerMsg::BuzzersLocked
+        | WsServerMsg::AnswerJudged {
+            outcome: JudgeOutcome::Reopened,
+            ..
+        } => {
+            events.extend(cancel(session, TimerKind::Answer));
+        }
+        WsServerMsg::HasBuzzedReset
+        | WsServerMsg::PhaseChanged { .. }
+        | WsServerMsg::AnswerJudged {
+            outcome: JudgeOutcome::Closed { .. },
+            ..
+        }
+        | WsServerMsg::AnswerRevealed { .. }
+        | WsServerMsg::DailyDoubleRevealed { .. }
+        | WsServerMsg::CellDone { .. }
+        | WsServerMsg::GameLoaded { .. }
+        | WsServerMsg::RoundStarted { .. }
+        | WsServerMsg::GameFinished
+        | WsServerMsg::TimerEnabledUpdate { enabled: false } => {
+            events.extend(cancel(session, TimerKind::Answer));
+            events.extend(cancel(session, TimerKind::Question));
+        }
+        _ => {}
+    }
+    events
+}
+
+/// Advances running timers by one second and applies expiry effects.
+///
+/// Returns the events to broadcast and whether anything beyond a plain tick changed.
+fn tick(session: &mut Session) -> (Vec<WsServerMsg>, bool) {
+    let mut events = Vec::new();
+    let mut expired = Vec::new();
+    for kind in [TimerKind::Question, TimerKind::Answer, TimerKind::Final] {
+        let Some(timer) = session.timers.slot_mut(kind).as_mut() else {
+            continue;
+        };
+        if !timer.running {
+            continue;
+        }
+        timer.remaining_secs = timer.remaining_secs.saturating_sub(1);
+        events.push(WsServerMsg::TimerTick {
+            kind,
+            remaining_secs: timer.remaining_secs,
+        });
+        if timer.remaining_secs == 0 {
+            timer.running = false;
+            expired.push(kind);
+        }
+    }
+
+    for kind in &expired {
+        events.push(WsServerMsg::TimerExpired { kind: *kind });
+        match kind {
+            // The final round closes on time whether or not `timer_enabled` is set.
+            TimerKind::Final => events.extend(final_round::close_answers(session)),
+            _ if !session.timer_enabled => {}
+            // Don't lock buzzers while a team is answering — let their timer finish.
+            TimerKind::Question => {
+                if !session.teams.iter().any(|team| team.buzz_lock_owned) {
+                    session.lock_buzzers();
+                    events.push(WsServerMsg::BuzzersLocked);
+                }
+            }
+            TimerKind::Answer => {
+                session.release_buzz();
+                events.push(WsServerMsg::BuzzReleased);
+                if session.timers.is_expired(TimerKind::Question) {
+                    session.lock_buzzers();
+                    events.push(WsServerMsg::BuzzersLocked);
+                }
+            }
+        }
+    }
+    (events, !expired.is_empty())
+}
+
+/// Spawns the session's ticker task if a timer is running and no ticker is active.
+///
+/// Runs on the session's actor, so only one ticker ever runs.
+pub fn ensure_ticker(actor: &mut SessionActor) {
+    let timers = &mut actor.session.timers;
+    if timers.ticker_active || !timers.any_running() {
+        return;
+    }
+    timers.ticker_active = true;
+    tokio::spawn(run_ticker(actor.state.clone(), actor.session_id.clone()));
+}
+
+async fn run_ticker(state: Arc<AppState>, session_id: String) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // The first tick completes immediately; skip it so the first second is a full second.
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let keep_running = actor::call(&state, &session_id, |actor| {
+            let (events, changed) = tick(&mut actor.session);
+            let keep_running = actor.session.timers.any_running();
+            if !keep_running {
+                actor.session.timers.ticker_active = false;
+            }
+            if changed {
+                actor.mark_dirty();
+            }
+            for event in &events {
+                match event {
+                    WsServerMsg::TimerTick { .. } => actor.notify(event),
+                    _ => actor.broadcast(event),
+                }
+            }
+            keep_running
+        })
+        .await;
+        if keep_running != Some(true) {
+            return;
+        }
+    }
+}
@@ -27,7 +27,7 @@ const App: React.FC = () => {
     useTeam();
   const { resetQuestions, setQuestions } = useQuestions();
   const { resetClickedCells, setRecentlyClickedIndex } = useBoard();
-  const { sessionId, startSession, closeSession, joinSession, setSessionId, wsRef, sessionLoading } =
+  const { sessionId, startSession, closeSession, joinSession, setSessionId, wsRef, sessionLoading, connectionState } =
     useSession();
   const { setIsHomePage } = usePage();
   const location = useLocation();
@@ -43,10 +43,11 @@ const App: React.FC = () => {
   useEffect(() => {
     const onHome = !player && location.pathname === "/";
     setIsHomePage(onHome);
-    if (sessionId && wsRef.current?.readyState === WebSocket.OPEN) {
-      wsRef.current.send(JSON.stringify({ type: "SetPage", page: onHome ? "home" : "question" }));
+    // Only the host's screen drives the game phase; players just follow it
+    if (!player && sessionId && connectionState === "connected" && wsRef.current?.readyState === WebSocket.OPEN) {
+      wsRef.current.send(JSON.stringify({ type: "SetPhase", phase: onHome ? "Board" : "QuestionOpen" }));
     }
-  }, [player, location.pathname, setIsHomePage, sessionId, wsRef]);
+  }, [player, location.pathname, setIsHomePage, sessionId, wsRef, connectionState]);
 
   useEffect(() => {
     if (sessionId === null) {
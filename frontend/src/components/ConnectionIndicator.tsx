@@ -1,8 +1,24 @@
-import React from 'react';
+import React, { useEffect, useState } from 'react';
 import { useSession } from '../context/SessionContext';
 
 const ConnectionIndicator: React.FC = () => {
-  const { connectionState, pingLatency, sessionId } = useSession();
+  const {
+    connectionState,
+    pingLatency,
+    sessionId,
+    sessionExpiresAt,
+    sessionExpiryIdle,
+    keepSessionAlive,
+  } = useSession();
+  const [now, setNow] = useState(Date.now());
+
+  // Tick once a second while an expiry warning is counting down
+  useEffect(() => {
+    if (sessionExpiresAt === null) return;
+    setNow(Date.now());
+    const timer = setInterval(() => setNow(Date.now()), 1000);
+    return () => clearInterval(timer);
+  }, [sessionExpiresAt]);
 
   // Don't show indicator if there's no session
   if (!sessionId) {
@@ -45,6 +61,9 @@ const ConnectionIndicator: React.FC = () => {
     }
   };
 
+  const expiresInSecs =
+    sessionExpiresAt !== null ? Math.ceil((sessionExpiresAt - now) / 1000) : 0;
+
   const shouldPulse = connectionState === 'reconnecting';
   const showPing = connectionState === 'connected' || connectionState === 'degraded';
 
@@ -62,6 +81,17 @@ const ConnectionIndicator: React.FC = () => {
           {pingLatency !== null ? `${pingLatency}ms` : '...'}
         </span>
       )}
+      {expiresInSecs > 0 && (
+        <span className="session-expiry-warning">
+          Session closes in {Math.floor(expiresInSecs / 60)}:
+          {String(expiresInSecs % 60).padStart(2, '0')}
+        </span>
+      )}
+      {expiresInSecs > 0 && sessionExpiryIdle && (
+        <button className="session-keep-alive" onClick={keepSessionAlive}>
+          Keep open
+        </button>
+      )}
     </div>
   );
 };
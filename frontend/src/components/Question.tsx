@@ -2,6 +2,7 @@ import React, { useState, useEffect, useRef } from "react";
 import { useNavigate } from "react-router-dom";
 import { useSettings } from "../context/SettingsContext";
 import { useTeam } from "../context/TeamContext";
+import { useSession } from "../context/SessionContext";
 import { Timer } from "lucide-react";
 import "../styles/Question.css";
 
@@ -26,7 +27,12 @@ const Question: React.FC<{
 }) => {
   const navigate = useNavigate();
   const { timerEnabled } = useSettings();
-  const { resetBuzzedTeams, releaseBuzzLock, teams } = useTeam();
+  const { resetBuzzedTeams, releaseBuzzLock, judgeAnswer, teams, earlyBuzzPenaltyMs, buzzersOpen, openBuzzers } =
+    useTeam();
+  const { sessionId, addWsListener, removeWsListener } = useSession();
+  // In a session the server owns both timers and applies lock/release itself;
+  // local intervals only run in solo mode.
+  const serverTimers = !!sessionId;
   const [timeLeft, setTimeLeft] = useState(TIMER_DURATION);
   const [timerExpired, setTimerExpired] = useState(false);
   const intervalRef = useRef<ReturnType<typeof setInterval> | null>(null);
@@ -51,9 +57,57 @@ const Question: React.FC<{
     }
   }, [timerEnabled, revealed]);
 
+  // Mirror the server's question/answer timers while in a session
+  useEffect(() => {
+    if (!serverTimers) return;
+    const handleTimerMessage = (event: MessageEvent) => {
+      let msg;
+      try {
+        msg = JSON.parse(event.data);
+      } catch {
+        return;
+      }
+      const isQuestion = msg.kind === "Question";
+      switch (msg.type) {
+        case "TimerStarted":
+          if (isQuestion) {
+            setTimeLeft(msg.duration_secs);
+            setTimerExpired(false);
+          } else {
+            setTeamTimeLeft(msg.duration_secs);
+          }
+          break;
+        case "TimerTick":
+        case "TimerPaused":
+        case "TimerResumed":
+          if (isQuestion) {
+            setTimeLeft(msg.remaining_secs);
+          } else {
+            setTeamTimeLeft(msg.remaining_secs);
+          }
+          break;
+        case "TimerExpired":
+          if (isQuestion) {
+            setTimeLeft(0);
+            setTimerExpired(true);
+          } else {
+            setTeamTimeLeft(null);
+          }
+          break;
+        case "TimerCancelled":
+          if (!isQuestion) {
+            setTeamTimeLeft(null);
+          }
+          break;
+      }
+    };
+    addWsListener(handleTimerMessage);
+    return () => removeWsListener(handleTimerMessage);
+  }, [serverTimers, addWsListener, removeWsListener]);
+
   // Tick the timer — does NOT pause when buzzLock is true
   useEffect(() => {
-    if (!timerEnabled || revealed || timerExpired) return;
+    if (serverTimers || !timerEnabled || revealed || timerExpired) return;
 
     intervalRef.current = setInterval(() => {
       setTimeLeft((prev) => {
@@ -77,10 +131,11 @@ const Question: React.FC<{
         intervalRef.current = null;
       }
     };
-  }, [timerEnabled, revealed, timerExpired]);
+  }, [serverTimers, timerEnabled, revealed, timerExpired]);
 
   // 15-second timer for buzzing team (only when on question page, timer enabled, and question not revealed)
   useEffect(() => {
+    if (serverTimers) return;
     if (!timerEnabled || revealed || !buzzLock) {
       // Clear team timer if conditions not met
       if (teamTimerRef.current) {
@@ -124,7 +179,7 @@ const Question: React.FC<{
         setTeamTimeLeft(null);
       }
     };
-  }, [timerEnabled, revealed, buzzLock, releaseBuzzLock]);
+  }, [serverTimers, timerEnabled, revealed, buzzLock, releaseBuzzLock]);
 
   const handleGoHome = () => {
     // Clear team timer
@@ -206,6 +261,21 @@ const Question: React.FC<{
         )}
         <div className="question-content">
           <h2 className="question-text">{questionText}</h2>
+          {serverTimers && !revealed && earlyBuzzPenaltyMs > 0 && !buzzersOpen && (
+            <button className="open-buzzers-button" onClick={openBuzzers}>
+              Open Buzzers
+            </button>
+          )}
+          {serverTimers && !revealed && buzzingTeam && (
+            <div className="judge-controls">
+              <button className="judge-button correct" onClick={() => judgeAnswer(true)}>
+                Correct
+              </button>
+              <button className="judge-button incorrect" onClick={() => judgeAnswer(false)}>
+                Incorrect
+              </button>
+            </div>
+          )}
           {revealed ? (
             <>
               <h3 className="answer fade-in">{answerText}</h3>
@@ -14,7 +14,7 @@
 import React, { useState, useEffect } from "react";
 import { Team } from "../context/TeamContext";
 import { useBoard } from "../context/BoardContext";
-import { Plus, Minus, X } from "lucide-react";
+import { Plus, Minus, X, Unlink } from "lucide-react";
 import "../styles/Score.css";
 
 const Score: React.FC<{
@@ -23,7 +23,8 @@ const Score: React.FC<{
   modifyTeam: (updatedTeam: Team) => void;
   managingTeams?: boolean;
   removeTeam?: () => void;
-}> = ({ team, controls, modifyTeam, managingTeams = false, removeTeam }) => {
+  releaseSeat?: () => void;
+}> = ({ team, controls, modifyTeam, managingTeams = false, removeTeam, releaseSeat }) => {
   const [inputValue, setInputValue] = useState<number>(0);
   const { targetScore } = useBoard(); // Access targetScore from context
   const [isEditingName, setIsEditingName] = useState(false); // Track if editing team name
@@ -72,6 +73,11 @@ const Score: React.FC<{
         ) : (
           <h4 onClick={() => setIsEditingName(true)}>{team.team_name}</h4>
         )}
+        {managingTeams && controls && releaseSeat && (
+          <button className="remove-team-button" onClick={releaseSeat} aria-label="Release Seat" title="Release this team's buzzer seat">
+            <Unlink size={14} />
+          </button>
+        )}
         {managingTeams && controls && removeTeam && (
           <button className="remove-team-button" onClick={removeTeam} aria-label="Remove Team">
             <X size={14} />
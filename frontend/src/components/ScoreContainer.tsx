@@ -1,6 +1,7 @@
 import React, { useRef, useEffect } from "react";
 import Score from "./Score";
 import { Team, useTeam } from "../context/TeamContext";
+import { useSession } from "../context/SessionContext";
 import "../styles/ScoreContainer.css";
 
 interface ScoreContainerProps {
@@ -22,7 +23,8 @@ const ScoreContainer: React.FC<ScoreContainerProps> = ({
   addTeam,
   removeTeam,
 }) => {
-  const { selectedTeam } = useTeam();
+  const { selectedTeam, undoScore, redoScore, releaseSeat } = useTeam();
+  const { sessionId } = useSession();
   const containerRef = useRef<HTMLDivElement>(null);
 
   // Handle mouse wheel for horizontal scrolling
@@ -121,10 +123,21 @@ const ScoreContainer: React.FC<ScoreContainerProps> = ({
                     modifyTeam={(updatedTeam) => modifyTeam(updatedTeam, index)}
                     managingTeams={managingTeams}
                     removeTeam={() => removeTeam(index)}
+                    releaseSeat={sessionId ? () => releaseSeat(index) : undefined}
                   />
                 )}
               </div>
             ))}
+            {sessionId && !player && (
+              <div className="score-history-controls">
+                <button onClick={undoScore} aria-label="Undo last score change" title="Undo score">
+                  ↶
+                </button>
+                <button onClick={redoScore} aria-label="Redo score change" title="Redo score">
+                  ↷
+                </button>
+              </div>
+            )}
             {managingTeams && !player && (
               <div className="add-team-wrapper">
                 <button className="add-team-button" onClick={addTeam} aria-label="Add Team">
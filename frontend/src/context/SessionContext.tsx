@@ -13,6 +13,7 @@ interface SessionTeam {
   buzz_lock_owned: boolean;
   has_buzzed: boolean;
   last_buzz_attempt: string | null;
+  locked_out_until?: string | null;
 }
 
 interface SessionState {
@@ -20,9 +21,16 @@ interface SessionState {
   buzz_lock: boolean;
   dark_mode: boolean;
   timer_enabled: boolean;
-  current_page: string;
+  early_buzz_penalty_ms?: number;
+  buzzers_open?: boolean;
+  phase: string;
   created_at: string;
   last_modified: string;
+  final_round?: {
+    stage: "Wagering" | "Answering" | "Reviewing" | "Finished";
+    category: string;
+    question: string;
+  } | null;
 }
 
 type ConnectionState = 
@@ -39,6 +47,10 @@ type ConnectionState =
  */
 interface SessionContextProps {
   sessionId: string | null;
+  /** Secret host token; present only on the device that started the session. */
+  hostToken: string | null;
+  /** Headers to send with host-only HTTP requests. */
+  authHeaders: () => Record<string, string>;
   sessionLoading: boolean;
   connectionState: ConnectionState;
   sessionState: SessionState | null;
@@ -56,6 +68,12 @@ interface SessionContextProps {
   sendPing: () => void;
   pingLatency: number | null;
   lastPingTime: number | null;
+  /** When the server said it will expire the session (epoch ms), if it has warned. */
+  sessionExpiresAt: number | null;
+  /** Whether the warned expiry is for idleness, which `keepSessionAlive` can put off. */
+  sessionExpiryIdle: boolean;
+  /** Asks the server to restart the session's idle countdown. */
+  keepSessionAlive: () => void;
 }
 
 const SessionContext = createContext<SessionContextProps | undefined>(undefined);
@@ -66,27 +84,55 @@ const API_URL = import.meta.env.VITE_API_URL || "http://127.0.0.1:3000";
  * Derives a WebSocket URL from the HTTP API URL.
  * Converts http:// → ws:// and https:// → wss://
  */
-function getWsUrl(sessionId: string): string {
+function getWsUrl(sessionId: string, hostToken: string | null, since: number | null): string {
   const wsBase = API_URL.replace(/^http/, "ws");
-  return `${wsBase}/session/${sessionId}/ws`;
+  const params = new URLSearchParams();
+  if (hostToken) params.set("host_token", hostToken);
+  if (since !== null) params.set("since", String(since));
+  const query = params.toString();
+  return `${wsBase}/session/${sessionId}/ws${query ? `?${query}` : ""}`;
 }
 
+/** Host tokens are kept per session so a host can refresh without losing control. */
+const hostTokenKey = (sessionId: string) => `hostToken:${sessionId}`;
+
 export const SessionProvider: React.FC<{ children: React.ReactNode }> = ({
   children,
 }) => {
   const [sessionId, setSessionId] = useState<string | null>(null);
+  const [hostToken, setHostToken] = useState<string | null>(null);
   const [sessionLoading, setSessionLoading] = useState<boolean>(false);
   const [connectionState, setConnectionState] = useState<ConnectionState>('disconnected');
   const [sessionState, setSessionState] = useState<SessionState | null>(null);
   const [lastPingTime, setLastPingTime] = useState<number | null>(null);
   const [pingLatency, setPingLatency] = useState<number | null>(null);
+  const [sessionExpiresAt, setSessionExpiresAt] = useState<number | null>(null);
+  const [sessionExpiryIdle, setSessionExpiryIdle] = useState<boolean>(false);
   const wsRef = useRef<WebSocket | null>(null);
   const onWsMessageRef = useRef<((event: MessageEvent) => void) | null>(null);
   const wsListenersRef = useRef<Set<(event: MessageEvent) => void>>(new Set());
   const reconnectTimerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
   const reconnectAttemptsRef = useRef(0);
+  // Delay the server asked for in ServerShuttingDown; used for the next reconnect only.
+  const reconnectHintRef = useRef<number | null>(null);
   const intentionalCloseRef = useRef(false);
   const pingIntervalRef = useRef<ReturnType<typeof setInterval> | null>(null);
+  // Receipt for the last Pong, reported on the next Ping so the server can
+  // complete its clock-sync estimate for this connection.
+  const lastPongRef = useRef<{ client_timestamp: string; received_at: string } | null>(null);
+  // Highest broadcast `seq` applied; sent as `?since=` on reconnect so the
+  // server can replay only what we missed.
+  const lastSeqRef = useRef<number | null>(null);
+
+  const buildPing = useCallback(
+    () =>
+      JSON.stringify({
+        type: "Ping",
+        client_timestamp: new Date().toISOString(),
+        last_pong: lastPongRef.current,
+      }),
+    []
+  );
 
   const setOnWsMessage = useCallback(
     (handler: ((event: MessageEvent) => void) | null) => {
@@ -119,7 +165,7 @@ const connectWs = useCallback(
         pingIntervalRef.current = null;
       }
 
-      const url = getWsUrl(id);
+      const url = getWsUrl(id, localStorage.getItem(hostTokenKey(id)), lastSeqRef.current);
       const ws = new WebSocket(url);
       wsRef.current = ws;
       setConnectionState('connecting');
@@ -129,22 +175,15 @@ const connectWs = useCallback(
          reconnectAttemptsRef.current = 0;
          
          // Send initial ping immediately
+         lastPongRef.current = null;
          if (ws.readyState === WebSocket.OPEN) {
-           const clientTimestamp = new Date().toISOString();
-           ws.send(JSON.stringify({ 
-             type: "Ping", 
-             client_timestamp: clientTimestamp 
-           }));
+           ws.send(buildPing());
          }
          
          // Start periodic ping for connection health monitoring (every 3s)
          pingIntervalRef.current = setInterval(() => {
            if (ws.readyState === WebSocket.OPEN) {
-             const clientTimestamp = new Date().toISOString();
-             ws.send(JSON.stringify({ 
-               type: "Ping", 
-               client_timestamp: clientTimestamp 
-             }));
+             ws.send(buildPing());
            }
          }, 3000); // Ping every 3s
        };
@@ -152,10 +191,35 @@ const connectWs = useCallback(
       ws.onmessage = (event) => {
         try {
           const msg = JSON.parse(event.data);
+          if (typeof msg.seq === "number") {
+            const lastSeq = lastSeqRef.current;
+            if (msg.type !== "FullState" && lastSeq !== null) {
+              if (msg.seq <= lastSeq) return; // Already applied
+              if (msg.seq > lastSeq + 1) {
+                // Missed a broadcast; reconnect to resync from lastSeq.
+                ws.close();
+                return;
+              }
+            }
+            lastSeqRef.current = msg.seq;
+          }
           if (msg.type === "FullState") {
             setSessionState(msg.session);
+          } else if (msg.type === "SessionExpiring") {
+            setSessionExpiresAt(Date.parse(msg.expires_at));
+            setSessionExpiryIdle(msg.reason === "Idle");
+          } else if (msg.type === "SessionExpiryCancelled") {
+            setSessionExpiresAt(null);
+          } else if (msg.type === "ServerShuttingDown") {
+            // A restart, not a failure: come back when the server suggests.
+            reconnectHintRef.current = msg.reconnect_after_ms;
+            reconnectAttemptsRef.current = 0;
           } else if (msg.type === "Pong") {
               const pongTime = Date.now();
+              lastPongRef.current = {
+                client_timestamp: msg.client_timestamp,
+                received_at: new Date(pongTime).toISOString(),
+              };
               const clientSentTime = new Date(msg.client_timestamp).getTime();
               const roundTrip = pongTime - clientSentTime;
               setPingLatency(roundTrip);
@@ -191,9 +255,11 @@ const connectWs = useCallback(
 
         setConnectionState('reconnecting');
         
-        // Exponential backoff: 1s, 2s, 4s, 8s, max 30s
+        // Exponential backoff: 1s, 2s, 4s, 8s, max 30s, unless the server
+        // told us when it will be back.
         const attempt = reconnectAttemptsRef.current;
-        const delay = Math.min(1000 * Math.pow(2, attempt), 30000);
+        const delay = reconnectHintRef.current ?? Math.min(1000 * Math.pow(2, attempt), 30000);
+        reconnectHintRef.current = null;
         reconnectAttemptsRef.current = attempt + 1;
 
         reconnectTimerRef.current = setTimeout(() => {
@@ -206,21 +272,25 @@ const connectWs = useCallback(
         setConnectionState('degraded');
       };
     },
-    [sessionId]
+    [sessionId, buildPing]
   );
 
   const sendPing = useCallback(() => {
     if (wsRef.current?.readyState === WebSocket.OPEN) {
-      const clientTimestamp = new Date().toISOString();
-      wsRef.current.send(JSON.stringify({ 
-        type: "Ping", 
-        client_timestamp: clientTimestamp 
-      }));
+      wsRef.current.send(buildPing());
+    }
+  }, [buildPing]);
+
+  const keepSessionAlive = useCallback(() => {
+    if (wsRef.current?.readyState === WebSocket.OPEN) {
+      wsRef.current.send(JSON.stringify({ type: "KeepAlive" }));
     }
   }, []);
 
 // Establish or tear down WebSocket when sessionId changes
   useEffect(() => {
+    lastSeqRef.current = null;
+    setSessionExpiresAt(null);
     if (sessionId) {
       intentionalCloseRef.current = false;
       setSessionState(null);
@@ -261,14 +331,24 @@ const connectWs = useCallback(
     };
   }, [sessionId]); // Remove connectWs from dependencies
 
+  // Pick up a stored host token whenever the session changes.
+  useEffect(() => {
+    setHostToken(sessionId ? localStorage.getItem(hostTokenKey(sessionId)) : null);
+  }, [sessionId]);
+
+  const authHeaders = useCallback((): Record<string, string> => {
+    return hostToken ? { Authorization: `Bearer ${hostToken}` } : {};
+  }, [hostToken]);
+
   const startSession = async () => {
     setSessionLoading(true);
     try {
       const response = await fetch(`${API_URL}/session/start`, {
         method: "POST",
       });
-      const id = await response.json();
-      setSessionId(id);
+      const { session_id, host_token } = await response.json();
+      localStorage.setItem(hostTokenKey(session_id), host_token);
+      setSessionId(session_id);
     } catch (error) {
       console.error("Error starting session:", error);
     } finally {
@@ -281,7 +361,9 @@ const connectWs = useCallback(
     try {
       await fetch(`${API_URL}/session/${sessionId}/close`, {
         method: "POST",
+        headers: authHeaders(),
       });
+      localStorage.removeItem(hostTokenKey(sessionId));
     } catch (error) {
       console.error("Error closing session:", error);
     }
@@ -315,6 +397,8 @@ const connectWs = useCallback(
     <SessionContext.Provider
       value={{
         sessionId,
+        hostToken,
+        authHeaders,
         sessionLoading,
         connectionState,
         sessionState,
@@ -329,6 +413,9 @@ const connectWs = useCallback(
         sendPing,
         pingLatency,
         lastPingTime,
+        sessionExpiresAt,
+        sessionExpiryIdle,
+        keepSessionAlive,
       }}
     >
       {children}
@@ -21,6 +21,19 @@ export interface Team {
   buzz_lock_owned: boolean;
   has_buzzed: boolean; // Track if team has buzzed for current question
   last_buzz_attempt: string | null;
+  locked_out_until?: string | null; // End of the team's early-buzz lockout
+}
+
+export type FinalStage = "Wagering" | "Answering" | "Reviewing" | "Finished";
+
+/**
+ * What a device can see of the final round. Other teams' wagers and answers
+ * stay on the server until the host reveals them.
+ */
+export interface FinalRoundView {
+  stage: FinalStage;
+  category: string;
+  question: string;
 }
 
 interface BuzzFeedback {
@@ -38,11 +51,24 @@ interface TeamContextProps {
   hasPlayedBuzzerRef: React.RefObject<boolean>;
   selectedTeam: number;
   setSelectedTeam: React.Dispatch<React.SetStateAction<number>>;
+  seatedTeam: number | null; // The team this player device holds a seat at
+  claimSeat: (index: number) => void; // Take a team's seat (player devices, session only)
+  releaseSeat: (index: number) => void; // Free a team's seat so its devices can pick again (host only)
   loading: boolean;
   addTeam: () => void;
   removeTeam: (index: number) => void;
   resetBuzzedTeams: () => void; // Reset has_buzzed when new question starts
+  undoScore: () => void; // Revert the last score change (session only)
+  redoScore: () => void; // Re-apply the last undone score change (session only)
+  judgeAnswer: (correct: boolean, points?: number) => void; // Judge the buzzed team's answer (session only)
+  finalRound: FinalRoundView | null;
+  submitFinalWager: (wager: number) => void; // Hidden final wager for the selected team
+  submitFinalAnswer: (answer: string) => void; // Hidden final answer for the selected team
   buzzFeedback: BuzzFeedback;
+  earlyBuzzPenaltyMs: number; // 0 when early-buzz penalties are off
+  buzzersOpen: boolean; // Whether the host has opened buzzers for the question
+  openBuzzers: () => void; // Open buzzers once the question has been read (session only)
+  lockouts: Record<number, number>; // Local time each locked-out team may buzz again
 }
 
 const defaultTeams: Team[] = [
@@ -60,8 +86,18 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
 }) => {
   const [teams, setTeams] = useState<Team[]>(defaultTeams);
   const [buzzLock, setBuzzLock] = useState(false);
+  const [finalRound, setFinalRound] = useState<FinalRoundView | null>(null);
+  const [earlyBuzzPenaltyMs, setEarlyBuzzPenaltyMs] = useState(0);
+  const [buzzersOpen, setBuzzersOpen] = useState(false);
+  const [lockouts, setLockouts] = useState<Record<number, number>>({});
   const [selectedTeam, setSelectedTeam] = useState<number>(0);
   const selectedTeamRef = useRef<number>(selectedTeam);
+  const [seatedTeam, setSeatedTeam] = useState<number | null>(null);
+  const seatedTeamRef = useRef<number | null>(seatedTeam);
+
+  useEffect(() => {
+    seatedTeamRef.current = seatedTeam;
+  }, [seatedTeam]);
 
   useEffect(() => {
     selectedTeamRef.current = selectedTeam;
@@ -74,8 +110,22 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
   const lastBuzzAttemptRef = useRef<Map<number, number>>(new Map());
 
 
-  const { sessionId, setSessionId, wsRef, setOnWsMessage, sessionState } = useSession();
-  const currentPageRef = useRef("home");
+  const {
+    sessionId,
+    setSessionId,
+    wsRef,
+    setOnWsMessage,
+    sessionState,
+    connectionState,
+    hostToken,
+    authHeaders,
+  } = useSession();
+  const phaseRef = useRef("Lobby");
+  const sessionIdRef = useRef(sessionId);
+
+  useEffect(() => {
+    sessionIdRef.current = sessionId;
+  }, [sessionId]);
 
   // Derive loading: true while waiting for initial session state from WS
   const loading = !!(sessionId && !sessionState);
@@ -88,13 +138,25 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
     buzz_lock: boolean;
     dark_mode: boolean;
     timer_enabled: boolean;
-    current_page?: string;
+    early_buzz_penalty_ms?: number;
+    buzzers_open?: boolean;
+    phase?: string;
     created_at: string;
     last_modified: string;
+    final_round?: FinalRoundView | null;
   }) => {
     if (session.teams) {
       setTeams(session.teams);
+      const now = Date.now();
+      const active: Record<number, number> = {};
+      session.teams.forEach((team, i) => {
+        const until = team.locked_out_until ? Date.parse(team.locked_out_until) : NaN;
+        if (until > now) active[i] = until;
+      });
+      setLockouts(active);
     }
+    setEarlyBuzzPenaltyMs(session.early_buzz_penalty_ms ?? 0);
+    setBuzzersOpen(!!session.buzzers_open);
     if (typeof session.buzz_lock === "boolean") {
       setBuzzLock(session.buzz_lock);
       if (session.buzz_lock && !hasPlayedBuzzerRef.current) {
@@ -106,16 +168,34 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
         hasPlayedBuzzerRef.current = false;
       }
     }
-    if (session.current_page) {
-      currentPageRef.current = session.current_page;
+    if (session.phase) {
+      phaseRef.current = session.phase;
     }
+    setFinalRound(
+      session.final_round
+        ? {
+            stage: session.final_round.stage,
+            category: session.final_round.category,
+            question: session.final_round.question,
+          }
+        : null
+    );
 }, []);
 
+  // Rollbacks for optimistic updates, keyed by request_id, run if the server
+  // acknowledges the request as rejected.
+  const pendingRequestsRef = useRef<Map<string, () => void>>(new Map());
+  const nextRequestIdRef = useRef(0);
+
   const sendWsMessage = useCallback(
-    (msg: object) => {
+    (msg: object, onRejected?: () => void) => {
       const ws = wsRef.current;
       if (ws && ws.readyState === WebSocket.OPEN) {
-        ws.send(JSON.stringify(msg));
+        const request_id = `req-${++nextRequestIdRef.current}`;
+        if (onRejected) {
+          pendingRequestsRef.current.set(request_id, onRejected);
+        }
+        ws.send(JSON.stringify({ ...msg, request_id }));
         return true;
       }
       return false;
@@ -136,10 +216,53 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
       // Fallback to HTTP
       fetch(`${API_URL}/session/${sessionId}/buzz/release`, {
         method: "POST",
+        headers: authHeaders(),
       }).catch(() => {});
     }
   }, [sessionId, sendWsMessage]);
 
+  /** Forgets this device's seat, e.g. once the host has released it. */
+  const forgetSeat = useCallback(() => {
+    if (sessionIdRef.current) {
+      localStorage.removeItem(`seatToken:${sessionIdRef.current}`);
+    }
+    setSeatedTeam(null);
+  }, []);
+
+  /**
+   * Player devices claim a team's seat; the server only accepts their buzzes
+   * for that team and keeps them there until the host releases the seat.
+   */
+  const claimSeat = useCallback(
+    (teamIndex: number) => {
+      if (!sessionId || hostToken) return;
+      setSelectedTeam(teamIndex);
+      sendWsMessage({ type: "ClaimSeat", team_index: teamIndex }, () => {
+        setSelectedTeam(seatedTeamRef.current ?? 0);
+      });
+    },
+    [sessionId, hostToken, sendWsMessage]
+  );
+
+  /**
+   * The seat token is kept so a reconnect restores the same seat. Re-sent
+   * after every reconnect; a token the host has released is forgotten.
+   */
+  useEffect(() => {
+    if (!sessionId || hostToken || connectionState !== "connected") return;
+    const seatToken = localStorage.getItem(`seatToken:${sessionId}`);
+    if (!seatToken) return;
+    sendWsMessage({ type: "ClaimSeat", seat_token: seatToken }, forgetSeat);
+  }, [sessionId, hostToken, connectionState, sendWsMessage, forgetSeat]);
+
+  const releaseSeat = useCallback(
+    (teamIndex: number) => {
+      if (!sessionId || !hostToken) return;
+      sendWsMessage({ type: "ReleaseSeat", team_index: teamIndex });
+    },
+    [sessionId, hostToken, sendWsMessage]
+  );
+
   
 
   /**
@@ -221,7 +344,7 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
               prev.map((team) => ({
                 ...team,
                 buzz_lock_owned: false,
-                has_buzzed: currentPageRef.current === "home" ? false : team.has_buzzed,
+                has_buzzed: phaseRef.current === "Board" ? false : team.has_buzzed,
               }))
             );
             hasPlayedBuzzerRef.current = false;
@@ -235,6 +358,78 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
             );
             break;
 
+          case "AnswerJudged":
+            setTeams((prev) =>
+              prev.map((team, i) => {
+                const scored = i === msg.team_index ? { ...team, score: msg.score } : team;
+                switch (msg.outcome?.kind) {
+                  case "PassedTo":
+                    return i === msg.outcome.team_index
+                      ? { ...scored, buzz_lock_owned: true, has_buzzed: true }
+                      : { ...scored, buzz_lock_owned: false };
+                  case "Reopened":
+                    return { ...scored, buzz_lock_owned: false };
+                  default:
+                    return { ...scored, buzz_lock_owned: false, has_buzzed: false };
+                }
+              })
+            );
+            setBuzzLock(msg.outcome?.kind === "PassedTo");
+            if (msg.outcome?.kind !== "PassedTo") {
+              hasPlayedBuzzerRef.current = false;
+            }
+            if (msg.outcome?.kind === "Closed") {
+              phaseRef.current = "Board";
+            }
+            break;
+
+          case "DailyDoubleRevealed":
+            // Buzzers stay locked while the picking team wagers
+            setBuzzLock(true);
+            setBuzzersOpen(false);
+            setTeams((prev) => prev.map((team) => ({ ...team, buzz_lock_owned: false, has_buzzed: false })));
+            break;
+
+          case "WagerPlaced":
+            setBuzzLock(true);
+            setTeams((prev) =>
+              prev.map((team, i) => ({ ...team, buzz_lock_owned: i === msg.team_index }))
+            );
+            break;
+
+          case "GameLoaded":
+          case "RoundStarted":
+          case "GameFinished":
+            // The server resets buzzers whenever the round changes
+            phaseRef.current = msg.type === "RoundStarted" ? msg.phase : msg.type === "GameFinished" ? "Finished" : "Board";
+            setBuzzLock(false);
+            setTeams((prev) => prev.map((team) => ({ ...team, buzz_lock_owned: false, has_buzzed: false })));
+            hasPlayedBuzzerRef.current = false;
+            break;
+
+          case "FinalStarted":
+            setBuzzLock(true);
+            setTeams((prev) => prev.map((team) => ({ ...team, buzz_lock_owned: false, has_buzzed: false })));
+            setFinalRound({ stage: "Wagering", category: msg.category, question: "" });
+            break;
+
+          case "FinalQuestionRevealed":
+            setFinalRound((prev) => prev && { ...prev, stage: "Answering", question: msg.question });
+            break;
+
+          case "FinalAnswersClosed":
+            setFinalRound((prev) => prev && { ...prev, stage: "Reviewing" });
+            break;
+
+          case "FinalJudged":
+            setTeams((prev) =>
+              prev.map((team, i) =>
+                i === msg.team_index ? { ...team, score: msg.score } : team
+              )
+            );
+            setFinalRound((prev) => prev && { ...prev, stage: msg.stage });
+            break;
+
           case "TeamNameUpdate":
             setTeams((prev) =>
               prev.map((team, i) =>
@@ -245,6 +440,23 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
              );
              break;
 
+           case "EarlyBuzzPenaltyUpdate":
+             setEarlyBuzzPenaltyMs(msg.penalty_ms);
+             break;
+
+           case "BuzzersOpened":
+             setBuzzersOpen(true);
+             break;
+
+           case "CellOpened":
+             setBuzzersOpen(false);
+             break;
+
+           case "TeamLockedOut":
+             // Count down from receipt so device clock skew doesn't matter
+             setLockouts((prev) => ({ ...prev, [msg.team_index]: Date.now() + msg.penalty_ms }));
+             break;
+
            case "BuzzersLocked":
              setBuzzLock(true);
              // No team owns the lock — timer expiry
@@ -253,12 +465,19 @@ export const TeamProvider: React.FC<{ children: React.ReactNode }> = ({
 
            case "HasBuzzedReset":
              setBuzzLock(false);
-             currentPageRef.current = "home";
              setTeams((prev) => prev.map((team) => ({ ...team, buzz_lock_owned: false, has_buzzed: false })));
              break;
 
-           case "PageUpdate":
-             currentPageRef.current = msg.page;
+           case "PhaseChanged":
+             phaseRef.current = msg.phase;
+             if (msg.phase === "QuestionOpen") {
+               setBuzzersOpen(false);
+             }
+             if (msg.phase === "Board") {
+               setBuzzLock(false);
+               setTeams((prev) => prev.map((team) => ({ ...team, buzz_lock_owned: false, has_buzzed: false })));
+               hasPlayedBuzzerRef.current = false;
+             }
              break;
 
            case "TeamAdded":
@@ -269,6 +488,12 @@ case "TeamRemoved":
                const newTeams = prev.filter((_, i) => i !== msg.team_index);
                return newTeams;
              });
+             // The server drops seats at a removed team and shifts later ones down
+             if (msg.team_index === seatedTeamRef.current) {
+               forgetSeat();
+             } else {
+               setSeatedTeam((seated) => (seated !== null && seated > msg.team_index ? seated - 1 : seated));
+             }
              // Adjust selectedTeam if it's now out of bounds
              setSelectedTeam((currentSelected) => {
                if (currentSelected === msg.team_index) {
@@ -281,7 +506,37 @@ case "TeamRemoved":
                return currentSelected;
              });
              break;
+          case "Ack": {
+            const rollback = pendingRequestsRef.current.get(msg.request_id);
+            pendingRequestsRef.current.delete(msg.request_id);
+            if (rollback && msg.result?.status === "Rejected") {
+              rollback();
+            }
+            break;
+          }
+          case "Error":
+            // Sent only to us when the server rejects one of our messages.
+            console.warn(`Server rejected message (${msg.code}): ${msg.message}`);
+            setBuzzFeedback({ visible: true, message: msg.message });
+            setTimeout(() => {
+              setBuzzFeedback(prev => ({ ...prev, visible: false }));
+            }, 3000);
+            break;
+          case "SeatClaimed":
+            if (sessionIdRef.current) {
+              localStorage.setItem(`seatToken:${sessionIdRef.current}`, msg.seat_token);
+            }
+            setSeatedTeam(msg.team_index);
+            setSelectedTeam(msg.team_index);
+            break;
+          case "SeatReleased":
+            if (msg.team_index === seatedTeamRef.current) {
+              forgetSeat();
+            }
+            break;
           case "SessionClosed":
+            setSeatedTeam(null);
+            setFinalRound(null);
             setSessionId(null);
             setTeams(defaultTeams);
             setBuzzLock(false);
@@ -291,7 +546,7 @@ case "TeamRemoved":
         // Ignore unparseable messages
       }
     },
-    [setSessionId]
+    [setSessionId, forgetSeat]
   );
   
   // Register WebSocket message handler when session is active
@@ -301,6 +556,7 @@ case "TeamRemoved":
     } else {
       setOnWsMessage(null);
       setTeams(defaultTeams);
+      setFinalRound(null);
       setBuzzLock(false);
     }
 
@@ -315,6 +571,7 @@ case "TeamRemoved":
       applyFullState(sessionState);
     } else if (!sessionId) {
       setTeams(defaultTeams);
+      setFinalRound(null);
       setBuzzLock(false);
     }
   }, [sessionState, sessionId, applyFullState]);
@@ -326,6 +583,46 @@ case "TeamRemoved":
     setTeams((prev) => prev.map((team) => ({ ...team, has_buzzed: false })));
   };
 
+  /**
+   * Undo/redo the last score change. The server keeps the score ledger, so these
+   * only work in a session; the resulting ScoreUpdate arrives as a broadcast.
+   */
+  const undoScore = () => {
+    sendWsMessage({ type: "Undo" });
+  };
+
+  const redoScore = () => {
+    sendWsMessage({ type: "Redo" });
+  };
+
+  /**
+   * Judge the answer of the team holding the buzz lock. The server scores it
+   * and passes, reopens or closes the question; the result arrives as AnswerJudged.
+   */
+  const judgeAnswer = (correct: boolean, points?: number) => {
+    sendWsMessage({ type: correct ? "JudgeCorrect" : "JudgeIncorrect", points: points ?? null });
+  };
+
+  /**
+   * Open buzzers after reading the question. With early-buzz penalties on,
+   * teams that buzz before this are locked out for a while.
+   */
+  const openBuzzers = () => {
+    sendWsMessage({ type: "OpenBuzzers" });
+  };
+
+  /**
+   * Final round submissions for the selected team. The server keeps them
+   * hidden from every other device until the host reveals them.
+   */
+  const submitFinalWager = (wager: number) => {
+    sendWsMessage({ type: "SubmitFinalWager", team_index: selectedTeam, wager });
+  };
+
+  const submitFinalAnswer = (answer: string) => {
+    sendWsMessage({ type: "SubmitFinalAnswer", team_index: selectedTeam, answer });
+  };
+
   /**
    * Buzz in for a team. Sends via WebSocket, falls back to HTTP if WS unavailable.
    */
@@ -348,18 +645,27 @@ case "TeamRemoved":
     }
 
     if (buzzLock) return;
+    if ((lockouts[teamIndex] ?? 0) > Date.now()) return;
 
     // Record client timestamp for this buzz attempt
     const clientTimestamp = new Date().toISOString();
     lastBuzzAttemptRef.current.set(teamIndex, Date.now());
 
-    // Try WebSocket first
+    // Try WebSocket first; undo the optimistic lock if the server rejects the buzz
+    const previousTeams = teams;
+    const previousBuzzLock = buzzLock;
     if (
-      sendWsMessage({ 
-        type: "BuzzIn", 
-        team_index: teamIndex,
-        client_timestamp: clientTimestamp 
-      })
+      sendWsMessage(
+        {
+          type: "BuzzIn",
+          team_index: teamIndex,
+          client_timestamp: clientTimestamp,
+        },
+        () => {
+          setTeams(previousTeams);
+          setBuzzLock(previousBuzzLock);
+        }
+      )
     ) {
       // Optimistic update - server will confirm
       setBuzzLock(true);
@@ -373,11 +679,15 @@ case "TeamRemoved":
       return;
     }
 
-    // Fallback to HTTP (HTTP won't get timing feedback)
+    // Fallback to HTTP (HTTP won't get timing feedback). Players authorize
+    // with the seat token from their last SeatClaimed.
     if (!sessionId) return;
+    const seatToken = hostToken ? null : localStorage.getItem(`seatToken:${sessionId}`);
+    if (!hostToken && !seatToken) return;
 
     fetch(`${API_URL}/session/${sessionId}/buzz/${teamIndex}`, {
       method: "POST",
+      headers: seatToken ? { Authorization: `Bearer ${seatToken}` } : authHeaders(),
     })
       .then((response) => {
         if (!response.ok) throw new Error();
@@ -426,6 +736,7 @@ case "TeamRemoved":
       method: "PUT",
       headers: {
         "Content-Type": "application/json",
+        ...authHeaders(),
       },
       body: JSON.stringify(team),
     })
@@ -495,10 +806,23 @@ case "TeamRemoved":
         loading,
         selectedTeam,
         setSelectedTeam,
+        seatedTeam,
+        claimSeat,
+        releaseSeat,
         addTeam,
         removeTeam,
         resetBuzzedTeams,
+        undoScore,
+        redoScore,
+        judgeAnswer,
+        finalRound,
+        submitFinalWager,
+        submitFinalAnswer,
         buzzFeedback,
+        earlyBuzzPenaltyMs,
+        buzzersOpen,
+        openBuzzers,
+        lockouts,
       }}
     >
       {children}
@@ -9,7 +9,23 @@ interface BuzzerPageProps {
 }
 
 const BuzzerPage: React.FC<BuzzerPageProps> = ({ buzzIn, teams }) => {
-  const {selectedTeam, setSelectedTeam, buzzLock, buzzFeedback } = useTeam();
+  const {
+    selectedTeam,
+    seatedTeam,
+    claimSeat,
+    buzzLock,
+    buzzFeedback,
+    finalRound,
+    submitFinalWager,
+    submitFinalAnswer,
+    lockouts,
+  } = useTeam();
+  const lockoutEnd = lockouts[selectedTeam] ?? 0;
+  const [lockoutLeft, setLockoutLeft] = useState(0);
+  const [finalWager, setFinalWager] = useState("");
+  const [finalAnswer, setFinalAnswer] = useState("");
+  const [finalSubmitted, setFinalSubmitted] = useState(false);
+  const finalOpen = finalRound?.stage === "Wagering" || finalRound?.stage === "Answering";
   const wakeLockRef = useRef<WakeLockSentinel | null>(null);
   const [isPressed, setIsPressed] = useState(false);
   const touchStartRef = useRef<number>(0);
@@ -43,7 +59,21 @@ const BuzzerPage: React.FC<BuzzerPageProps> = ({ buzzIn, teams }) => {
     };
   }, []);
 
+  // Count down an early-buzz lockout for the selected team
+  useEffect(() => {
+    const update = () => setLockoutLeft(Math.max(0, lockoutEnd - Date.now()));
+    update();
+    if (lockoutEnd <= Date.now()) return;
+    const interval = setInterval(update, 100);
+    const timeout = setTimeout(() => clearInterval(interval), lockoutEnd - Date.now() + 100);
+    return () => {
+      clearInterval(interval);
+      clearTimeout(timeout);
+    };
+  }, [lockoutEnd]);
+
   const handleBuzz = () => {
+    if (seatedTeam === null) return;
     buzzIn(selectedTeam);
   };
 
@@ -64,7 +94,7 @@ const BuzzerPage: React.FC<BuzzerPageProps> = ({ buzzIn, teams }) => {
     
     // Only trigger buzz if it was a quick tap with minimal movement
     if (touchDuration < 200 && verticalMovement < 10) {
-      buzzIn(selectedTeam);
+      handleBuzz();
     }
   };
 
@@ -76,8 +106,30 @@ const BuzzerPage: React.FC<BuzzerPageProps> = ({ buzzIn, teams }) => {
     }
   };
 
+  // A device picks its team once; only the host can release the seat.
   const handleTeamChange = (event: React.ChangeEvent<HTMLSelectElement>) => {
-    setSelectedTeam(Number(event.target.value));
+    claimSeat(Number(event.target.value));
+  };
+
+  // Each final stage starts with an empty, unsubmitted form.
+  useEffect(() => {
+    setFinalSubmitted(false);
+    if (finalRound?.stage === "Wagering") {
+      setFinalWager("");
+      setFinalAnswer("");
+    }
+  }, [finalRound?.stage]);
+
+  const handleFinalSubmit = (event: React.FormEvent) => {
+    event.preventDefault();
+    if (finalRound?.stage === "Wagering") {
+      const wager = parseInt(finalWager, 10);
+      if (Number.isNaN(wager)) return;
+      submitFinalWager(wager);
+    } else {
+      submitFinalAnswer(finalAnswer);
+    }
+    setFinalSubmitted(true);
   };
 
   return (
@@ -85,9 +137,16 @@ const BuzzerPage: React.FC<BuzzerPageProps> = ({ buzzIn, teams }) => {
       <div className="team-selector">
         <select
           id="team-select"
-          value={selectedTeam}
+          value={seatedTeam ?? ""}
           onChange={handleTeamChange}
+          disabled={seatedTeam !== null}
+          title={seatedTeam !== null ? "Ask the host to release this seat to switch teams" : undefined}
         >
+          {seatedTeam === null && (
+            <option value="" disabled>
+              Pick your team
+            </option>
+          )}
           {teams.map((team, index) => (
             <option key={index} value={index}>
               {team.team_name}
@@ -95,8 +154,43 @@ const BuzzerPage: React.FC<BuzzerPageProps> = ({ buzzIn, teams }) => {
           ))}
         </select>
       </div>
+      {finalOpen && finalRound ? (
+        <form className="final-panel" onSubmit={handleFinalSubmit}>
+          <h2 className="final-category">{finalRound.category}</h2>
+          {finalRound.stage === "Wagering" ? (
+            <input
+              type="number"
+              inputMode="numeric"
+              min={0}
+              max={Math.max(teams[selectedTeam]?.score ?? 0, 0)}
+              placeholder="Your wager"
+              value={finalWager}
+              onChange={(e) => setFinalWager(e.target.value)}
+            />
+          ) : (
+            <>
+              <p className="final-question">{finalRound.question}</p>
+              <textarea
+                placeholder="Your answer"
+                value={finalAnswer}
+                onChange={(e) => setFinalAnswer(e.target.value)}
+              />
+            </>
+          )}
+          <button type="submit" className="final-submit">
+            {finalSubmitted ? "Update" : "Submit"}
+          </button>
+          {finalSubmitted && <span className="final-status">Submitted — hidden until the host reveals it</span>}
+        </form>
+      ) : (
+      <>
+      {lockoutLeft > 0 && (
+        <div className="lockout-countdown">
+          Too early! Locked out for {(lockoutLeft / 1000).toFixed(1)}s
+        </div>
+      )}
       <button 
-        className={`buzzer-button ${buzzLock ? "lock_owned" : ""} ${teams[selectedTeam]?.buzz_lock_owned ? "lock_win" : ""} ${isPressed ? "pressed" : ""}`}
+        className={`buzzer-button ${buzzLock ? "lock_owned" : ""} ${teams[selectedTeam]?.buzz_lock_owned ? "lock_win" : ""} ${isPressed ? "pressed" : ""} ${lockoutLeft > 0 ? "locked_out" : ""}`}
         onClick={handleBuzz}
         onTouchStart={handleTouchStart}
         onTouchEnd={handleTouchEnd}
@@ -104,6 +198,8 @@ const BuzzerPage: React.FC<BuzzerPageProps> = ({ buzzIn, teams }) => {
         onTouchCancel={() => setIsPressed(false)}
       >
       </button>
+      </>
+      )}
       <BuzzFeedback 
         visible={buzzFeedback.visible}
         message={buzzFeedback.message}